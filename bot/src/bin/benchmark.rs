@@ -0,0 +1,82 @@
+// Plays many games with the bot for every mode and difficulty and prints how the scores spread
+use bot::{play, ErrorModel, MAX_LENGTH};
+use clap::Parser;
+use program::settings::{Difficulty, GameMode, Settings};
+
+// Width of the longest histogram bar, in characters
+const BAR_WIDTH: usize = 50;
+
+#[derive(Parser)]
+#[command(about = "Score distributions of a simulated player for every preset")]
+struct Args {
+    /// Games per mode and difficulty
+    #[arg(long, default_value_t = 1000)]
+    games: u64,
+    /// Chance to forget each symbol of a sequence
+    #[arg(long, default_value_t = 0.02)]
+    forget: f64,
+    /// Longest sequence the player can remember
+    #[arg(long, default_value_t = 7)]
+    span: usize,
+    /// Time the player needs to learn one symbol while the sequence is shown
+    #[arg(long, default_value_t = 250)]
+    ms_per_symbol: u32,
+    /// Seed of the first game, the others follow it
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+fn main() -> Result<(), bot::Error> {
+    let args = Args::parse();
+    let model = ErrorModel {
+        forget: args.forget,
+        span: args.span,
+        ms_per_symbol: args.ms_per_symbol,
+    };
+    println!(
+        "{} games per preset, forget {}, span {}, {} ms per symbol",
+        args.games, model.forget, model.span, model.ms_per_symbol
+    );
+    for mode in GameMode::ALL {
+        for difficulty in Difficulty::ALL {
+            let settings = Settings { mode, difficulty };
+            let mut scores = (0..args.games)
+                .map(|game| play(settings, model, args.seed.wrapping_add(game)))
+                .collect::<Result<Vec<f32>, _>>()?;
+            scores.sort_by(f32::total_cmp);
+            println!();
+            println!("{} {}", mode.name(), difficulty.name());
+            print_distribution(&scores);
+        }
+    }
+    Ok(())
+}
+
+fn print_distribution(scores: &[f32]) {
+    if scores.is_empty() {
+        return;
+    }
+    let percentile = |p: usize| scores[(scores.len() - 1) * p / 100];
+    let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+    println!(
+        "  mean {:.2}  p10 {:.2}  median {:.2}  p90 {:.2}  max {:.2}",
+        mean,
+        percentile(10),
+        percentile(50),
+        percentile(90),
+        percentile(100)
+    );
+
+    // One bucket per whole number of sequences cleared
+    let mut buckets = vec![0usize; MAX_LENGTH + 1];
+    for &score in scores {
+        buckets[(score as usize).min(MAX_LENGTH)] += 1;
+    }
+    let first = buckets.iter().position(|&count| count > 0).unwrap_or(0);
+    let last = buckets.iter().rposition(|&count| count > 0).unwrap_or(0);
+    let largest = buckets.iter().copied().max().unwrap_or(1);
+    for (score, &count) in buckets.iter().enumerate().take(last + 1).skip(first) {
+        let bar = "#".repeat(count * BAR_WIDTH / largest);
+        println!("  {:>3} {:>6} {}", score, count, bar);
+    }
+}
@@ -0,0 +1,244 @@
+// A simulated player, to see how scores change with the difficulty without asking people to play
+// thousands of games. It learns each sequence from the game's events instead of the display.
+use core::convert::Infallible;
+use embedded_graphics::geometry::Size;
+use embedded_graphics::primitives::Rectangle;
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::framebuffer::{Framebuffer, TooLarge};
+use program::game::Game;
+use program::settings::Settings;
+
+// Games end here even if the bot could go on, a perfect player never loses
+pub const MAX_LENGTH: usize = 64;
+// Frames to hold and to release each button, long enough for the debouncer
+const PRESS_FRAMES: u32 = 4;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ErrorModel {
+    // Chance to forget each symbol of a sequence, which is then guessed
+    pub forget: f64,
+    // Longest sequence the bot can hold in memory, symbols after it are guessed
+    pub span: usize,
+    // Time needed to learn one symbol, symbols there was no time for are guessed
+    pub ms_per_symbol: u32,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    // Not a failure, the only way a game ends
+    GameOver(f32),
+    Format,
+    // The display is larger than the game's frame
+    TooLarge,
+}
+
+impl From<Infallible> for Error {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+impl From<core::fmt::Error> for Error {
+    fn from(_value: core::fmt::Error) -> Self {
+        Self::Format
+    }
+}
+
+impl From<TooLarge> for Error {
+    fn from(_value: TooLarge) -> Self {
+        Self::TooLarge
+    }
+}
+
+enum Phase {
+    // Pushing both buttons in the menu
+    Starting,
+    // Waiting for the sequence to be shown and hidden
+    Watching,
+    // Pressing the remembered sequence, one button per press cycle
+    Answering { answers: Vec<bool>, next: usize, frame: u32 },
+    Finished(f32),
+}
+
+pub struct Bot {
+    display: Framebuffer,
+    model: ErrorModel,
+    rng: fastrand::Rng,
+    seed: u64,
+    settings: Settings,
+    answers: Vec<bool>,
+    phase: Phase,
+}
+
+impl Bot {
+    pub fn new(model: ErrorModel, seed: u64) -> Self {
+        Bot {
+            display: Framebuffer::new(Size::new(128, 32)).unwrap(),
+            model,
+            // Not the game's seed, or the bot's guesses would follow the game's sequences
+            rng: fastrand::Rng::with_seed(!seed),
+            seed,
+            settings: Settings::default(),
+            answers: Vec::new(),
+            phase: Phase::Starting,
+        }
+    }
+
+    // What the bot will answer for this sequence
+    fn remember(&mut self, sequence: &[bool]) -> Vec<bool> {
+        let study_ms = self.settings.difficulty.display_ms(sequence.len());
+        let studied = (study_ms / self.model.ms_per_symbol.max(1)) as usize;
+        let limit = self.model.span.min(studied);
+        sequence
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| {
+                if index >= limit || self.rng.f64() < self.model.forget {
+                    self.rng.bool()
+                } else {
+                    value
+                }
+            })
+            .collect()
+    }
+}
+
+impl AbstractDevice for Bot {
+    type Display = Framebuffer;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Error> {
+        let released = Inputs::default();
+        match &mut self.phase {
+            Phase::Starting => Ok(Inputs {
+                button1_down: true,
+                button2_down: true,
+            }),
+            Phase::Watching => Ok(released),
+            Phase::Answering {
+                answers,
+                next,
+                frame,
+            } => {
+                let Some(&value) = answers.get(*next) else {
+                    return Ok(released);
+                };
+                // Release first, the last press of the same button may still be held
+                let down = *frame >= PRESS_FRAMES;
+                *frame += 1;
+                if *frame == 2 * PRESS_FRAMES {
+                    *frame = 0;
+                    *next += 1;
+                }
+                Ok(Inputs {
+                    button1_down: down && !value,
+                    button2_down: down && value,
+                })
+            }
+            Phase::Finished(score) => Err(Error::GameOver(*score)),
+        }
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn delay_ms(&mut self, _ms: u32) {}
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::GameStarted { settings, .. } => {
+                self.settings = settings;
+                self.phase = Phase::Watching;
+            }
+            GameEvent::SequenceShown { sequence, .. } => {
+                if sequence.len() > MAX_LENGTH {
+                    self.phase = Phase::Finished((sequence.len() - 1) as f32);
+                } else {
+                    self.answers = self.remember(sequence);
+                    self.phase = Phase::Watching;
+                }
+            }
+            GameEvent::SequenceHidden => {
+                if let Phase::Watching = self.phase {
+                    self.phase = Phase::Answering {
+                        answers: core::mem::take(&mut self.answers),
+                        next: 0,
+                        frame: 0,
+                    };
+                }
+            }
+            GameEvent::Input { .. } => {}
+            GameEvent::GameOver { score } => self.phase = Phase::Finished(score),
+        }
+    }
+}
+
+// Plays one game and returns its score
+pub fn play(settings: Settings, model: ErrorModel, seed: u64) -> Result<f32, Error> {
+    let mut game = Game::with_settings(Bot::new(model, seed), settings)?;
+    match game.run_game() {
+        Err(Error::GameOver(score)) => Ok(score),
+        Err(error) => Err(error),
+        Ok(()) => unreachable!("games only end with an error"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{play, ErrorModel, MAX_LENGTH};
+    use program::settings::{Difficulty, GameMode, Settings};
+
+    const PERFECT: ErrorModel = ErrorModel {
+        forget: 0.0,
+        span: usize::MAX,
+        ms_per_symbol: 1,
+    };
+
+    #[test]
+    fn test_perfect_bot_reaches_the_limit() {
+        for mode in GameMode::ALL {
+            for difficulty in Difficulty::ALL {
+                let settings = Settings { mode, difficulty };
+                assert_eq!(play(settings, PERFECT, 1).unwrap(), MAX_LENGTH as f32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_span_limits_score() {
+        let model = ErrorModel { span: 6, ..PERFECT };
+        for seed in 0..20 {
+            let score = play(Settings::default(), model, seed).unwrap();
+            // Everything up to the span is remembered, after it only lucky guesses help
+            assert!((6.0..12.0).contains(&score), "{}", score);
+        }
+    }
+
+    #[test]
+    fn test_study_time_limits_score() {
+        // 400 ms per symbol learns 5 symbols in the 2000 ms of normal, 3 in the 1200 ms of hard
+        let model = ErrorModel {
+            ms_per_symbol: 400,
+            ..PERFECT
+        };
+        let total = |difficulty| -> f32 {
+            let settings = Settings {
+                mode: GameMode::Classic,
+                difficulty,
+            };
+            (0..50).map(|seed| play(settings, model, seed).unwrap()).sum()
+        };
+        assert!(total(Difficulty::Hard) < total(Difficulty::Normal));
+    }
+}
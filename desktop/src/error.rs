@@ -1,3 +1,4 @@
+use program::framebuffer::{TooLarge, MAX_HEIGHT, MAX_WIDTH};
 use std::convert::Infallible;
 
 #[derive(Debug)]
@@ -6,6 +7,13 @@ pub enum Error {
     Infallible,
     // Format error
     Format,
+    // Error talking to the terminal or reading a file
+    Io(#[allow(dead_code)] std::io::Error),
+    // Invalid command line or config file option
+    Config(#[allow(dead_code)] String),
+    // Error setting up SDL
+    #[cfg(feature = "sdl")]
+    Sdl(#[allow(dead_code)] String),
     // Exit out of the application
     Quit,
 }
@@ -22,3 +30,17 @@ impl From<core::fmt::Error> for Error {
     }
 }
 
+impl From<TooLarge> for Error {
+    fn from(TooLarge(size): TooLarge) -> Self {
+        Self::Config(format!(
+            "display size {}x{} is larger than {}x{}",
+            size.width, size.height, MAX_WIDTH, MAX_HEIGHT
+        ))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
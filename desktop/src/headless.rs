@@ -0,0 +1,45 @@
+use crate::error::Error;
+use embedded_graphics::geometry::Size;
+use embedded_graphics::primitives::Rectangle;
+use program::abstract_device::{AbstractDevice, Inputs};
+use program::framebuffer::Framebuffer;
+
+// No window, no terminal and no buttons, for playing replays into a video on CI or over SSH
+pub struct Device {
+    display: Framebuffer,
+    seed: u64,
+}
+
+impl Device {
+    pub fn new(size: Size, seed: u64) -> Result<Self, Error> {
+        Ok(Device {
+            display: Framebuffer::new(size)?,
+            seed,
+        })
+    }
+}
+
+impl AbstractDevice for Device {
+    type Display = Framebuffer;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Error> {
+        Ok(Inputs::default())
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn delay_ms(&mut self, _ms: u32) {}
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+        Ok(())
+    }
+}
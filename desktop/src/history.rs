@@ -0,0 +1,439 @@
+use crate::error::Error;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::primitives::Rectangle;
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const CSV_HEADER: &str = "timestamp,seed,mode,difficulty,score,levels,sequences,inputs,reaction_ms";
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    // When the game started, RFC 3339 in UTC
+    pub timestamp: String,
+    // The RNG's state as the game started, `--seed` with it plays the same sequences
+    pub seed: u64,
+    pub mode: String,
+    pub difficulty: String,
+    pub score: f32,
+    pub levels: Vec<LevelRecord>,
+}
+
+// One showing of a sequence. In lives mode a level is repeated after each mistake.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub level: usize,
+    // Buttons as digits, "112" is button 1, button 1, button 2
+    pub sequence: String,
+    pub inputs: String,
+    // Time before each input, from the sequence being hidden or the previous input. Counted in the
+    // game's delays like video.rs does, so replays give the same times.
+    pub reaction_ms: Vec<u64>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    fn of(path: &Path) -> Format {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::JsonLines,
+        }
+    }
+}
+
+// Passes everything through to another device and appends a record of each finished game to a file
+pub struct History<D> {
+    device: D,
+    path: PathBuf,
+    game: Option<GameRecord>,
+    // The game's delays since the last event
+    since_event_ms: u64,
+    // Events can't fail, so errors writing the file are returned from the next get_inputs
+    error: Option<io::Error>,
+}
+
+impl<D: AbstractDevice> History<D> {
+    pub fn new(device: D, path: PathBuf) -> Self {
+        History {
+            device,
+            path,
+            game: None,
+            since_event_ms: 0,
+            error: None,
+        }
+    }
+
+    fn append(&self, game: &GameRecord) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        match Format::of(&self.path) {
+            Format::JsonLines => writeln!(file, "{}", serde_json::to_string(game)?),
+            Format::Csv => {
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "{}", CSV_HEADER)?;
+                }
+                writeln!(file, "{}", to_csv(game))
+            }
+        }
+    }
+}
+
+impl<D: AbstractDevice> AbstractDevice for History<D>
+where
+    D::Error: From<io::Error>,
+{
+    type Display = D::Display;
+    type Error = D::Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, D::Error> {
+        if let Some(error) = self.error.take() {
+            return Err(error.into());
+        }
+        self.device.get_inputs()
+    }
+
+    fn set_led(&mut self, new_state: bool) {
+        self.device.set_led(new_state)
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.device.set_rgb_led(color)
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.since_event_ms += ms as u64;
+        self.device.delay_ms(ms)
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.device.get_rng_seed()
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        self.device.display()
+    }
+
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), D::Error> {
+        self.device.flush_display(area)
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        self.device.on_event(event);
+        match event {
+            GameEvent::GameStarted { settings, seed } => {
+                self.game = Some(GameRecord {
+                    timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                    seed,
+                    mode: settings.mode.name().into(),
+                    difficulty: settings.difficulty.name().into(),
+                    score: 0.0,
+                    levels: Vec::new(),
+                });
+            }
+            GameEvent::SequenceShown { level, sequence } => {
+                if let Some(game) = &mut self.game {
+                    game.levels.push(LevelRecord {
+                        level,
+                        sequence: sequence.iter().map(|&value| button_digit(value)).collect(),
+                        inputs: String::new(),
+                        reaction_ms: Vec::new(),
+                    });
+                }
+            }
+            GameEvent::SequenceHidden => {}
+            GameEvent::Input { value, .. } => {
+                if let Some(level) = self.game.as_mut().and_then(|game| game.levels.last_mut()) {
+                    level.inputs.push(button_digit(value));
+                    level.reaction_ms.push(self.since_event_ms);
+                }
+            }
+            GameEvent::GameOver { score } => {
+                if let Some(mut game) = self.game.take() {
+                    game.score = score;
+                    if let Err(error) = self.append(&game) {
+                        self.error = Some(error);
+                    }
+                }
+            }
+        }
+        self.since_event_ms = 0;
+    }
+
+    fn take_settings(&mut self) -> Option<Settings> {
+        self.device.take_settings()
+    }
+}
+
+fn button_digit(value: bool) -> char {
+    if value {
+        '2'
+    } else {
+        '1'
+    }
+}
+
+// Lists within a level are separated by `;`, levels by spaces, so every field stays one CSV cell
+fn to_csv(game: &GameRecord) -> String {
+    let join = |field: fn(&LevelRecord) -> String| {
+        game.levels.iter().map(field).collect::<Vec<_>>().join(" ")
+    };
+    format!(
+        "{},{},{},{},{},{},{},{},{}",
+        game.timestamp,
+        game.seed,
+        game.mode,
+        game.difficulty,
+        game.score,
+        game.levels.iter().map(|level| level.level.to_string()).collect::<Vec<_>>().join(" "),
+        join(|level| level.sequence.clone()),
+        join(|level| level.inputs.clone()),
+        join(|level| {
+            let times: Vec<String> = level.reaction_ms.iter().map(|ms| ms.to_string()).collect();
+            times.join(";")
+        }),
+    )
+}
+
+fn from_csv(line: &str) -> Option<GameRecord> {
+    let fields: Vec<&str> = line.split(',').collect();
+    let [timestamp, seed, mode, difficulty, score, levels, sequences, inputs, reaction_ms] =
+        fields[..]
+    else {
+        return None;
+    };
+    // Empty inputs still take a place in the list, unlike what split_whitespace would do
+    let split = |list: &'_ str| -> Vec<String> { list.split(' ').map(String::from).collect() };
+    let levels: Vec<usize> = levels
+        .split_whitespace()
+        .map(|level| level.parse().ok())
+        .collect::<Option<_>>()?;
+    let (sequences, inputs, reaction_ms) = (split(sequences), split(inputs), split(reaction_ms));
+    let levels = levels
+        .into_iter()
+        .enumerate()
+        .map(|(index, level)| {
+            let reaction_ms = reaction_ms.get(index)?;
+            Some(LevelRecord {
+                level,
+                sequence: sequences.get(index)?.clone(),
+                inputs: inputs.get(index)?.clone(),
+                reaction_ms: reaction_ms
+                    .split(';')
+                    .filter(|ms| !ms.is_empty())
+                    .map(|ms| ms.parse().ok())
+                    .collect::<Option<_>>()?,
+            })
+        })
+        .collect::<Option<_>>()?;
+    Some(GameRecord {
+        timestamp: timestamp.into(),
+        seed: seed.parse().ok()?,
+        mode: mode.into(),
+        difficulty: difficulty.into(),
+        score: score.parse().ok()?,
+        levels,
+    })
+}
+
+fn read(path: &Path) -> Result<Vec<GameRecord>, Error> {
+    let text = fs::read_to_string(path)?;
+    let format = Format::of(path);
+    let mut games = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || (format == Format::Csv && line == CSV_HEADER) {
+            continue;
+        }
+        let game = match format {
+            Format::JsonLines => serde_json::from_str(line).ok(),
+            Format::Csv => from_csv(line),
+        };
+        let invalid = || Error::Config(format!("{}:{}: invalid record", path.display(), index + 1));
+        games.push(game.ok_or_else(invalid)?);
+    }
+    Ok(games)
+}
+
+// Games, mean and best score and mean reaction time for one mode and difficulty
+#[derive(PartialEq, Debug)]
+struct Summary {
+    mode: String,
+    difficulty: String,
+    games: usize,
+    mean_score: f32,
+    best_score: f32,
+    mean_reaction_ms: Option<u64>,
+}
+
+fn summarise(games: &[GameRecord]) -> Vec<Summary> {
+    let mut summaries: Vec<Summary> = Vec::new();
+    let mut reactions: Vec<Vec<u64>> = Vec::new();
+    for game in games {
+        let index = match summaries
+            .iter()
+            .position(|summary| summary.mode == game.mode && summary.difficulty == game.difficulty)
+        {
+            Some(index) => index,
+            None => {
+                summaries.push(Summary {
+                    mode: game.mode.clone(),
+                    difficulty: game.difficulty.clone(),
+                    games: 0,
+                    mean_score: 0.0,
+                    best_score: 0.0,
+                    mean_reaction_ms: None,
+                });
+                reactions.push(Vec::new());
+                summaries.len() - 1
+            }
+        };
+        let summary = &mut summaries[index];
+        summary.games += 1;
+        summary.mean_score += game.score;
+        summary.best_score = summary.best_score.max(game.score);
+        for level in &game.levels {
+            reactions[index].extend(&level.reaction_ms);
+        }
+    }
+    for (summary, reactions) in summaries.iter_mut().zip(reactions) {
+        summary.mean_score /= summary.games as f32;
+        if !reactions.is_empty() {
+            summary.mean_reaction_ms = Some(reactions.iter().sum::<u64>() / reactions.len() as u64);
+        }
+    }
+    summaries
+}
+
+pub fn print_stats(path: &Path) -> Result<(), Error> {
+    let games = read(path)?;
+    let (Some(first), Some(last)) = (games.first(), games.last()) else {
+        println!("No games in {}", path.display());
+        return Ok(());
+    };
+    let plural = if games.len() == 1 { "" } else { "s" };
+    println!("{} game{} from {} to {}", games.len(), plural, first.timestamp, last.timestamp);
+    println!();
+    println!(
+        "{:<8} {:<10} {:>5} {:>10} {:>10} {:>13}",
+        "mode", "difficulty", "games", "mean score", "best score", "mean reaction"
+    );
+    for summary in summarise(&games) {
+        let reaction = match summary.mean_reaction_ms {
+            Some(ms) => format!("{} ms", ms),
+            None => "-".into(),
+        };
+        println!(
+            "{:<8} {:<10} {:>5} {:>10.2} {:>10.2} {:>13}",
+            summary.mode,
+            summary.difficulty,
+            summary.games,
+            summary.mean_score,
+            summary.best_score,
+            reaction
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::headless;
+    use crate::history::{from_csv, summarise, to_csv, GameRecord, History, LevelRecord, Summary};
+    use embedded_graphics::geometry::Size;
+    use program::abstract_device::{AbstractDevice, GameEvent};
+    use program::settings::Settings;
+    use std::path::PathBuf;
+
+    fn game(mode: &str, score: f32, reaction_ms: Vec<u64>) -> GameRecord {
+        GameRecord {
+            timestamp: "2026-10-19T09:30:00Z".into(),
+            seed: 42,
+            mode: mode.into(),
+            difficulty: "normal".into(),
+            score,
+            levels: vec![
+                LevelRecord {
+                    level: 1,
+                    sequence: "112".into(),
+                    inputs: "112".into(),
+                    reaction_ms,
+                },
+                LevelRecord {
+                    level: 2,
+                    sequence: "2121".into(),
+                    inputs: "".into(),
+                    reaction_ms: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let game = game("classic", 3.0, vec![800, 350, 420]);
+        let line = to_csv(&game);
+        assert_eq!(
+            line,
+            "2026-10-19T09:30:00Z,42,classic,normal,3,1 2,112 2121,112 ,800;350;420 "
+        );
+        assert_eq!(from_csv(&line), Some(game));
+        assert_eq!(from_csv("2026-10-19T09:30:00Z,42,classic"), None);
+    }
+
+    #[test]
+    fn test_records_game_seed_and_delays() {
+        let device = headless::Device::new(Size::new(128, 32), 1).unwrap();
+        let mut history = History::new(device, PathBuf::from("unused.jsonl"));
+        let settings = Settings::default();
+        history.on_event(GameEvent::GameStarted { settings, seed: 7 });
+        history.on_event(GameEvent::SequenceShown { level: 1, sequence: &[false, true] });
+        history.delay_ms(500);
+        history.on_event(GameEvent::SequenceHidden);
+        history.delay_ms(300);
+        history.delay_ms(150);
+        history.on_event(GameEvent::Input { index: 0, value: false, correct: true });
+        history.delay_ms(200);
+        history.on_event(GameEvent::Input { index: 1, value: true, correct: true });
+        let game = history.game.unwrap();
+        assert_eq!(game.seed, 7);
+        assert_eq!(game.levels[0].inputs, "12");
+        assert_eq!(game.levels[0].reaction_ms, [450, 200]);
+    }
+
+    #[test]
+    fn test_summarise() {
+        let games = [
+            game("classic", 3.0, vec![800, 400]),
+            game("lives", 5.0, Vec::new()),
+            game("classic", 4.0, vec![300]),
+        ];
+        assert_eq!(
+            summarise(&games),
+            [
+                Summary {
+                    mode: "classic".into(),
+                    difficulty: "normal".into(),
+                    games: 2,
+                    mean_score: 3.5,
+                    best_score: 4.0,
+                    mean_reaction_ms: Some(500),
+                },
+                Summary {
+                    mode: "lives".into(),
+                    difficulty: "normal".into(),
+                    games: 1,
+                    mean_score: 5.0,
+                    best_score: 5.0,
+                    mean_reaction_ms: None,
+                },
+            ]
+        );
+    }
+}
@@ -1,114 +1,79 @@
 mod error;
-use embedded_graphics_simulator::{BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window};
-use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics_simulator::sdl2::Keycode;
-use std::thread;
-use std::time::Duration;
-use embedded_graphics::geometry::Size;
-use rand::RngCore;
-use program::abstract_device::{AbstractDevice, Inputs};
+mod headless;
+mod history;
+mod options;
+mod replay;
+#[cfg(feature = "sdl")]
+mod simulator;
+mod terminal;
+mod video;
+use clap::Parser;
+use program::abstract_device::AbstractDevice;
+use program::settings::Settings;
 use crate::error::Error;
+use crate::history::History;
+use crate::options::{Cli, Command, Config, Frontend, Session};
+use crate::replay::{Recorder, Replay};
+use crate::video::Video;
 use program::game::Game;
+use std::io::LineWriter;
+use std::mem;
 
 
 fn main() -> Result<(), Error> {
-    let display = SimulatorDisplay::<BinaryColor>::new(Size::new(128, 32));
-    let output_settings = OutputSettingsBuilder::new()
-        .theme(BinaryColorTheme::OledBlue)
-        .build();
-    let window = Window::new("Hello World", &output_settings);
-    let seed = rand::rng().next_u64();
-    let device = Device {
-        simulator_display: display,
-        window,
-        has_updated: false,
-        inputs: Inputs::default(),
-        seed,
-    };
-    let mut game = Game::new(device)?;
-    let result = game.run_game();
-        
-    if let Err(Error::Quit) = result {
-    Ok(())
-    } else {
-        result
+    let mut cli = Cli::parse();
+    if let Some(Command::Stats { file }) = cli.command.take() {
+        return history::print_stats(&file);
+    }
+    let mut config = Config::load(cli)?;
+    match config.frontend {
+        #[cfg(feature = "sdl")]
+        Frontend::Sdl => start(simulator::Device::new(&config)?, &mut config),
+        #[cfg(not(feature = "sdl"))]
+        Frontend::Sdl => unreachable!("rejected when loading the config"),
+        Frontend::Terminal => start(terminal::Device::new(&config)?, &mut config),
+        Frontend::Headless => {
+            let device = headless::Device::new(config.size, config.seed)?;
+            start(device, &mut config)
+        }
     }
 }
 
-struct Device {
-    simulator_display: SimulatorDisplay<BinaryColor>,
-    window: Window,
-    has_updated: bool,
-    inputs: Inputs,
-    seed: u64,
+// Wraps the frontend's device in the ones the options ask for, innermost first
+fn start<Device: AbstractDevice<Error = Error>>(device: Device, config: &mut Config) -> Result<(), Error> {
+    match config.history.take() {
+        Some(path) => play(History::new(device, path), config),
+        None => play(device, config),
+    }
 }
 
-impl AbstractDevice for Device {
-    type Display = SimulatorDisplay<BinaryColor>;
-    type Error = Error;
-
-    fn get_inputs(&mut self) -> Result<Inputs, Error> {
-        if !self.has_updated {
-            self.has_updated = true;
-            self.window.update(&self.simulator_display);
+fn play<Device: AbstractDevice<Error = Error>>(device: Device, config: &mut Config) -> Result<(), Error> {
+    let settings = config.settings;
+    match mem::replace(&mut config.session, Session::Play) {
+        Session::Play => film(device, config),
+        Session::Record(file) => film(Recorder::new(device, LineWriter::new(file), settings), config),
+        Session::Replay { log, step } => {
+            let quit_at_end = config.frontend == Frontend::Headless;
+            film(Replay::new(device, log, step, quit_at_end), config)
         }
-        for event in self.window.events() {
-            match event {
-                SimulatorEvent::KeyUp {
-                    keycode,
-                    keymod: _,
-                    repeat: _,
-                } => match keycode {
-                    Keycode::Z => {
-                        self.inputs.button1_down = false;
-                    }
-                    Keycode::X => {
-                        self.inputs.button2_down = false;
-                    }
-                    _ => (),
-                },
-                SimulatorEvent::KeyDown {
-                    keycode,
-                    keymod: _,
-                    repeat: _,
-                } => match keycode {
-                    Keycode::Z => {
-                        self.inputs.button1_down = true;
-                    }
-                    Keycode::X => {
-                        self.inputs.button2_down = true;
-                    }
-                    Keycode::Escape => {
-                        return Err(Error::Quit);
-                    }
-                    _ => (),
-                },
-                SimulatorEvent::Quit => {
-                    return Err(Error::Quit);
-                }
-                _ => {}
-            }
-        }
-        Ok(self.inputs)
-    }
-
-    fn set_led(&mut self, _new_state: bool) {}
-
-    fn delay_ms(&mut self, ms: u32) {
-        thread::sleep(Duration::from_millis(ms as u64))
     }
+}
 
-    fn get_rng_seed(&mut self) -> u64 {
-        self.seed
+// Outside of a replay, so the video still sees the delays the replay skips
+fn film<Device: AbstractDevice<Error = Error>>(device: Device, config: &mut Config) -> Result<(), Error> {
+    match config.video.take() {
+        Some(path) => run(Video::new(device, path, config.theme, config.scale)?, config.settings),
+        None => run(device, config.settings),
     }
+}
 
-    fn display(&mut self) -> &mut Self::Display {
-        &mut self.simulator_display
-    }
+fn run<Device: AbstractDevice<Error = Error>>(device: Device, settings: Settings) -> Result<(), Error> {
+    let mut game = Game::with_settings(device, settings)?;
+    let result = game.run_game();
 
-    fn flush_display(&mut self) -> Result<(), Error> {
-        self.window.update(&self.simulator_display);
-        self.has_updated = true;
-        Ok(())
+    if let Err(Error::Quit) = result {
+    Ok(())
+    } else {
+        result
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,415 @@
+use crate::error::Error;
+use crate::replay::Log;
+use crate::video;
+use crate::terminal::Glyphs;
+use clap::{Parser, ValueEnum};
+use embedded_graphics::geometry::Size;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use program::framebuffer::{MAX_HEIGHT, MAX_WIDTH};
+use program::game::{MIN_HEIGHT, MIN_WIDTH};
+use program::settings::{Difficulty, GameMode, Settings};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Parser)]
+#[command(about = "Sequence memory game on the desktop")]
+pub struct Cli {
+    /// TOML file with any of the options below, without the leading dashes. Options given on
+    /// the command line take precedence over it.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Write the seed and every button change to FILE, to reproduce the session later
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Play a session written with --record. The buttons work again once it is over.
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Pause the replay before each button change until a button is pressed
+    #[arg(long, requires = "replay")]
+    pub step: bool,
+
+    /// Save what the display shows as an animated GIF, or an APNG if FILE ends in .png, in the
+    /// chosen theme and scale
+    #[arg(long, value_name = "FILE")]
+    pub video: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub options: Options,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Summarise a file written with --history
+    Stats { file: PathBuf },
+}
+
+#[derive(clap::Args, Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Where to play, headless only plays a replay [default: sdl, or terminal when built without
+    /// SDL]
+    #[arg(long, value_enum)]
+    pub frontend: Option<Frontend>,
+    /// Display width in pixels, from 64 to 128 [default: 128]
+    #[arg(long)]
+    pub width: Option<u32>,
+    /// Display height in pixels, from 16 to 64 [default: 32]
+    #[arg(long)]
+    pub height: Option<u32>,
+    /// Window pixels per display pixel [default: 1]
+    #[arg(long)]
+    pub scale: Option<u32>,
+    /// Display colours [default: oled-blue]
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+    /// Characters the terminal frontend draws pixels with [default: half-block]
+    #[arg(long, value_enum)]
+    pub glyphs: Option<Glyphs>,
+    /// Key for the first button, a character or a name like space or left [default: z]
+    #[arg(long, value_name = "KEY")]
+    pub button1_key: Option<String>,
+    /// Key for the second button [default: x]
+    #[arg(long, value_name = "KEY")]
+    pub button2_key: Option<String>,
+    /// Game controller buttons and triggers for the first button, by their SDL names
+    /// [default: x,a,leftshoulder,lefttrigger]
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub button1_pad: Option<Vec<String>>,
+    /// Game controller buttons and triggers for the second button
+    /// [default: b,y,rightshoulder,righttrigger]
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub button2_pad: Option<Vec<String>>,
+    /// Draw clickable buttons below the display in the window
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub on_screen_buttons: Option<bool>,
+    /// Seed for the sequences, to play the same game again [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Append a record of each finished game to FILE, as CSV if it ends in .csv and JSON Lines
+    /// otherwise
+    #[arg(long, value_name = "FILE")]
+    pub history: Option<PathBuf>,
+    /// Game mode: classic or lives [default: classic]
+    #[arg(long)]
+    #[serde(deserialize_with = "from_name")]
+    pub mode: Option<GameMode>,
+    /// Difficulty preset: easy, normal or hard [default: normal]
+    #[arg(long)]
+    #[serde(deserialize_with = "from_name")]
+    pub difficulty: Option<Difficulty>,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Frontend {
+    // Window through embedded-graphics-simulator
+    Sdl,
+    Terminal,
+    // Nothing shown, plays a replay as fast as possible and quits at its end
+    Headless,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    Default,
+    Inverted,
+    LcdWhite,
+    LcdGreen,
+    LcdBlue,
+    OledWhite,
+    OledBlue,
+}
+
+impl Theme {
+    // Colours of an off and an on pixel, the same as the simulator's themes
+    pub fn colors(self) -> (Rgb888, Rgb888) {
+        match self {
+            Theme::Default => (Rgb888::BLACK, Rgb888::WHITE),
+            Theme::Inverted => (Rgb888::WHITE, Rgb888::BLACK),
+            Theme::LcdWhite => (Rgb888::new(245, 245, 245), Rgb888::new(32, 32, 32)),
+            Theme::LcdGreen => (Rgb888::new(120, 185, 50), Rgb888::new(32, 32, 32)),
+            Theme::LcdBlue => (Rgb888::new(70, 80, 230), Rgb888::new(230, 230, 255)),
+            Theme::OledWhite => (Rgb888::new(20, 20, 20), Rgb888::WHITE),
+            Theme::OledBlue => (Rgb888::new(0, 20, 40), Rgb888::new(0, 210, 255)),
+        }
+    }
+}
+
+// Options with the defaults filled in
+pub struct Config {
+    pub session: Session,
+    pub video: Option<PathBuf>,
+    pub frontend: Frontend,
+    pub size: Size,
+    // Scales the pixels of the window and of videos
+    pub scale: u32,
+    pub theme: Theme,
+    pub glyphs: Glyphs,
+    pub keys: [String; 2],
+    // Only the window reads game controllers and shows on-screen buttons
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub pad_buttons: [Vec<String>; 2],
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub on_screen_buttons: bool,
+    pub seed: u64,
+    pub settings: Settings,
+    pub history: Option<PathBuf>,
+}
+
+pub enum Session {
+    Play,
+    Record(File),
+    Replay { log: Log, step: bool },
+}
+
+impl Config {
+    pub fn load(cli: Cli) -> Result<Config, Error> {
+        let replay = match cli.replay {
+            Some(path) => Some(
+                Log::parse(&fs::read_to_string(&path)?)
+                    .map_err(|error| Error::Config(format!("{}: {}", path.display(), error)))?,
+            ),
+            None => None,
+        };
+        let mut options = cli.options;
+        if let Some(path) = cli.config {
+            let file = fs::read_to_string(&path)?;
+            let file_options: Options = toml::from_str(&file)
+                .map_err(|error| Error::Config(format!("{}: {}", path.display(), error)))?;
+            options = options.or(file_options);
+        }
+
+        let default_frontend = if cfg!(feature = "sdl") {
+            Frontend::Sdl
+        } else {
+            Frontend::Terminal
+        };
+        let frontend = options.frontend.unwrap_or(default_frontend);
+        if frontend == Frontend::Sdl && !cfg!(feature = "sdl") {
+            return Err(Error::Config(
+                "built without the sdl feature, use --frontend terminal".into(),
+            ));
+        }
+        if frontend == Frontend::Headless && replay.is_none() {
+            return Err(Error::Config(
+                "the headless frontend only plays replays, add --replay FILE".into(),
+            ));
+        }
+        if let Some(path) = &cli.video {
+            if video::Format::of(path).is_none() {
+                return Err(Error::Config(format!(
+                    "{}: videos can be .gif or .png files",
+                    path.display()
+                )));
+            }
+        }
+        let size = Size::new(options.width.unwrap_or(128), options.height.unwrap_or(32));
+        // The game still runs on smaller screens, but its HUD and sequences don't fit on them
+        if !(MIN_WIDTH..=MAX_WIDTH).contains(&size.width)
+            || !(MIN_HEIGHT..=MAX_HEIGHT).contains(&size.height)
+        {
+            return Err(Error::Config(format!(
+                "display size {}x{} is not between {}x{} and {}x{}",
+                size.width, size.height, MIN_WIDTH, MIN_HEIGHT, MAX_WIDTH, MAX_HEIGHT
+            )));
+        }
+        let scale = options.scale.unwrap_or(1);
+        if scale == 0 {
+            return Err(Error::Config("scale must be at least 1".into()));
+        }
+
+        let mut seed = options.seed.unwrap_or_else(rand::random);
+        let mut settings = Settings {
+            mode: options.mode.unwrap_or(GameMode::Classic),
+            difficulty: options.difficulty.unwrap_or(Difficulty::Normal),
+        };
+        // A replay only plays back the same way with the game it was recorded with
+        if let Some(log) = &replay {
+            seed = log.seed;
+            settings = log.settings;
+        }
+
+        // Only once everything else is valid, as creating the file empties an earlier recording
+        let session = if let Some(path) = cli.record {
+            Session::Record(File::create(path)?)
+        } else if let Some(log) = replay {
+            Session::Replay {
+                log,
+                step: cli.step,
+            }
+        } else {
+            Session::Play
+        };
+
+        Ok(Config {
+            session,
+            video: cli.video,
+            frontend,
+            size,
+            scale,
+            theme: options.theme.unwrap_or(Theme::OledBlue),
+            glyphs: options.glyphs.unwrap_or(Glyphs::HalfBlock),
+            keys: [
+                options.button1_key.unwrap_or_else(|| "z".into()),
+                options.button2_key.unwrap_or_else(|| "x".into()),
+            ],
+            pad_buttons: [
+                options.button1_pad.unwrap_or_else(|| {
+                    ["x", "a", "leftshoulder", "lefttrigger"].map(String::from).into()
+                }),
+                options.button2_pad.unwrap_or_else(|| {
+                    ["b", "y", "rightshoulder", "righttrigger"].map(String::from).into()
+                }),
+            ],
+            on_screen_buttons: options.on_screen_buttons.unwrap_or(false),
+            seed,
+            settings,
+            history: options.history,
+        })
+    }
+}
+
+impl Options {
+    // Each option from self, or from fallback where self doesn't set it
+    fn or(self, fallback: Options) -> Options {
+        Options {
+            frontend: self.frontend.or(fallback.frontend),
+            width: self.width.or(fallback.width),
+            height: self.height.or(fallback.height),
+            scale: self.scale.or(fallback.scale),
+            theme: self.theme.or(fallback.theme),
+            glyphs: self.glyphs.or(fallback.glyphs),
+            button1_key: self.button1_key.or(fallback.button1_key),
+            button2_key: self.button2_key.or(fallback.button2_key),
+            button1_pad: self.button1_pad.or(fallback.button1_pad),
+            button2_pad: self.button2_pad.or(fallback.button2_pad),
+            on_screen_buttons: self.on_screen_buttons.or(fallback.on_screen_buttons),
+            seed: self.seed.or(fallback.seed),
+            history: self.history.or(fallback.history),
+            mode: self.mode.or(fallback.mode),
+            difficulty: self.difficulty.or(fallback.difficulty),
+        }
+    }
+}
+
+// Game modes and difficulties are named the same in the config file as on the command line
+fn from_name<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<Option<T>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse()
+        .map(Some)
+        .map_err(|_| D::Error::custom(format!("unknown value `{}`", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::headless;
+    use crate::options::{Cli, Config, Frontend, Theme};
+    use clap::Parser;
+    use program::settings::{Difficulty, GameMode};
+
+    fn write_temp(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("visual-memory-test-{}", name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().into()
+    }
+
+    #[test]
+    fn test_command_line_overrides_file() {
+        let path = write_temp(
+            "overrides.toml",
+            "frontend = \"terminal\"\nheight = 64\ntheme = \"lcd-green\"\nmode = \"lives\"\nseed = 7\n",
+        );
+        let cli = Cli::parse_from([
+            "desktop",
+            "--height",
+            "16",
+            "--config",
+            &path,
+            "--difficulty",
+            "hard",
+        ]);
+        let config = Config::load(cli).unwrap();
+
+        assert!(config.frontend == Frontend::Terminal);
+        assert_eq!((config.size.width, config.size.height), (128, 16));
+        assert!(config.theme == Theme::LcdGreen);
+        assert_eq!(config.seed, 7);
+        assert_eq!(config.settings.mode, GameMode::Lives);
+        assert_eq!(config.settings.difficulty, Difficulty::Hard);
+        assert_eq!(config.keys, ["z", "x"]);
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let path = write_temp("unknown.toml", "colour = \"red\"\n");
+        let cli = Cli::parse_from(["desktop", "--config", &path]);
+        assert!(Config::load(cli).is_err());
+        let path = write_temp("mode.toml", "mode = \"endless\"\n");
+        let cli = Cli::parse_from(["desktop", "--config", &path]);
+        assert!(Config::load(cli).is_err());
+    }
+
+    #[test]
+    fn test_invalid_config_keeps_recording() {
+        let recording = write_temp("kept.replay", "visual-memory replay 1\nseed 1\n");
+        let cli = Cli::parse_from(["desktop", "--record", &recording, "--width", "500"]);
+        assert!(Config::load(cli).is_err());
+        let contents = std::fs::read_to_string(&recording).unwrap();
+        assert_eq!(contents, "visual-memory replay 1\nseed 1\n");
+    }
+
+    #[test]
+    fn test_display_sizes() {
+        for (width, height) in [(63, 32), (129, 32), (128, 15), (128, 65)] {
+            let cli = Cli::parse_from([
+                "desktop",
+                "--frontend",
+                "terminal",
+                "--width",
+                &width.to_string(),
+                "--height",
+                &height.to_string(),
+            ]);
+            assert!(Config::load(cli).is_err(), "{}x{}", width, height);
+        }
+
+        // Starts a game at every accepted edge size, and plays it through a mistake
+        for mode in ["classic", "lives"] {
+            let replay = write_temp(
+                &format!("sizes-{}.replay", mode),
+                &format!(
+                    "visual-memory replay 1\nseed 1\nmode {}\n5 0 11\n6 0 00\n\
+                     100 0 10\n110 0 00\n120 0 01\n130 0 00\n140 0 01\n150 0 00\n400 0 end\n",
+                    mode
+                ),
+            );
+            for (width, height) in [(64, 16), (128, 16), (64, 64), (128, 64)] {
+                let cli = Cli::parse_from([
+                    "desktop",
+                    "--frontend",
+                    "headless",
+                    "--replay",
+                    &replay,
+                    "--width",
+                    &width.to_string(),
+                    "--height",
+                    &height.to_string(),
+                ]);
+                let mut config = Config::load(cli).unwrap();
+                let device = headless::Device::new(config.size, config.seed).unwrap();
+                let result = crate::start(device, &mut config);
+                assert!(result.is_ok(), "{}x{}", width, height);
+            }
+        }
+    }
+}
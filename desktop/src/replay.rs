@@ -0,0 +1,453 @@
+use crate::error::Error;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::primitives::Rectangle;
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::settings::Settings;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+const HEADER: &str = "visual-memory replay 1";
+
+// Everything needed to play a session again: the game only depends on the seed, the settings and
+// the inputs it reads each frame
+pub struct Log {
+    pub seed: u64,
+    pub settings: Settings,
+    pub changes: Vec<Change>,
+    // Frame at which the session was quit, missing from logs of sessions that crashed
+    pub end: Option<u64>,
+}
+
+// The inputs from `frame` (counting calls to get_inputs) on. The time is the sum of the delays
+// until then, only there for people reading or stepping through the log.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Change {
+    pub frame: u64,
+    pub ms: u64,
+    pub inputs: Inputs,
+}
+
+impl Log {
+    fn header(seed: u64, settings: Settings) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{}", HEADER);
+        let _ = writeln!(text, "seed {}", seed);
+        let _ = writeln!(text, "mode {}", settings.mode.name());
+        let _ = writeln!(text, "difficulty {}", settings.difficulty.name());
+        let _ = writeln!(text, "# frame ms buttons, or end");
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Log, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(format!("not a replay, expected `{}` on the first line", HEADER));
+        }
+        let mut seed = None;
+        let mut settings = Settings::default();
+        let mut changes: Vec<Change> = Vec::new();
+        let mut end = None;
+        for (number, line) in lines {
+            if end.is_some() {
+                return Err(format!("line {}: more after the end", number));
+            }
+            let invalid = || format!("line {}: invalid `{}`", number, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["seed", value] => seed = Some(value.parse().map_err(|_| invalid())?),
+                ["mode", value] => settings.mode = value.parse().map_err(|_| invalid())?,
+                ["difficulty", value] => {
+                    settings.difficulty = value.parse().map_err(|_| invalid())?
+                }
+                [frame, _, "end"] => end = Some(frame.parse().map_err(|_| invalid())?),
+                [frame, ms, buttons] => {
+                    let change = Change {
+                        frame: frame.parse().map_err(|_| invalid())?,
+                        ms: ms.parse().map_err(|_| invalid())?,
+                        inputs: parse_buttons(buttons).ok_or_else(invalid)?,
+                    };
+                    if changes.last().is_some_and(|last| last.frame >= change.frame) {
+                        return Err(format!("line {}: frames are out of order", number));
+                    }
+                    changes.push(change);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Log {
+            seed: seed.ok_or("missing seed")?,
+            settings,
+            changes,
+            end,
+        })
+    }
+
+    // First frame the log has no inputs for
+    fn end_frame(&self) -> u64 {
+        match (self.end, self.changes.last()) {
+            (Some(end), _) => end,
+            (None, Some(last)) => last.frame + 1,
+            (None, None) => 0,
+        }
+    }
+}
+
+// Buttons as two digits, 1 for down
+fn format_buttons(inputs: Inputs) -> String {
+    format!("{}{}", inputs.button1_down as u8, inputs.button2_down as u8)
+}
+
+fn parse_buttons(buttons: &str) -> Option<Inputs> {
+    let down = |digit| match digit {
+        b'0' => Some(false),
+        b'1' => Some(true),
+        _ => None,
+    };
+    match buttons.as_bytes() {
+        [button1, button2] => Some(Inputs {
+            button1_down: down(*button1)?,
+            button2_down: down(*button2)?,
+        }),
+        _ => None,
+    }
+}
+
+// Passes everything through to another device and writes the seed and each input change to a log
+pub struct Recorder<D, W> {
+    device: D,
+    log: W,
+    seed: u64,
+    frame: u64,
+    ms: u64,
+    inputs: Inputs,
+}
+
+impl<D: AbstractDevice, W: Write> Recorder<D, W> {
+    pub fn new(mut device: D, mut log: W, settings: Settings) -> Self {
+        // The game asks for the seed when the first game starts, but the inputs before that are
+        // logged too and have to come after the header
+        let seed = device.get_rng_seed();
+        if let Err(error) = log.write_all(Log::header(seed, settings).as_bytes()) {
+            eprintln!("Could not write the replay: {}", error);
+        }
+        Recorder {
+            device,
+            log,
+            seed,
+            frame: 0,
+            ms: 0,
+            inputs: Inputs::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn into_log(self) -> W {
+        self.log
+    }
+}
+
+impl<D: AbstractDevice, W: Write> AbstractDevice for Recorder<D, W>
+where
+    D::Error: From<io::Error>,
+{
+    type Display = D::Display;
+    type Error = D::Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, D::Error> {
+        let inputs = match self.device.get_inputs() {
+            Ok(inputs) => inputs,
+            Err(error) => {
+                // Most likely the player quit. Either way the replay should stop here too.
+                let _ = writeln!(self.log, "{} {} end", self.frame, self.ms);
+                return Err(error);
+            }
+        };
+        if inputs != self.inputs {
+            self.inputs = inputs;
+            writeln!(self.log, "{} {} {}", self.frame, self.ms, format_buttons(inputs))?;
+        }
+        self.frame += 1;
+        Ok(inputs)
+    }
+
+    fn set_led(&mut self, new_state: bool) {
+        self.device.set_led(new_state)
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.device.set_rgb_led(color)
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.ms += ms as u64;
+        self.device.delay_ms(ms)
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        self.device.display()
+    }
+
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), D::Error> {
+        self.device.flush_display(area)
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        self.device.on_event(event)
+    }
+
+    fn take_settings(&mut self) -> Option<Settings> {
+        self.device.take_settings()
+    }
+}
+
+// Feeds a log back into the game, without waiting for its delays. The wrapped device still shows
+// the display, and takes over the buttons once the log runs out, unless the replay quits there.
+pub struct Replay<D> {
+    device: D,
+    log: Log,
+    next_change: usize,
+    // Wait for a button press on the device before each change of the inputs
+    step: bool,
+    quit_at_end: bool,
+    finished: bool,
+    frame: u64,
+    ms: u64,
+    inputs: Inputs,
+}
+
+impl<D: AbstractDevice> Replay<D> {
+    pub fn new(device: D, log: Log, step: bool, quit_at_end: bool) -> Self {
+        Replay {
+            device,
+            log,
+            next_change: 0,
+            step,
+            quit_at_end,
+            finished: false,
+            frame: 0,
+            ms: 0,
+            inputs: Inputs::default(),
+        }
+    }
+
+    fn wait_for_press(&mut self) -> Result<(), D::Error> {
+        let is_down = |inputs: Inputs| inputs.button1_down || inputs.button2_down;
+        while is_down(self.device.get_inputs()?) {
+            self.device.delay_ms(10);
+        }
+        while !is_down(self.device.get_inputs()?) {
+            self.device.delay_ms(10);
+        }
+        Ok(())
+    }
+}
+
+impl<D: AbstractDevice> AbstractDevice for Replay<D>
+where
+    D::Error: From<Error>,
+{
+    type Display = D::Display;
+    type Error = D::Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, D::Error> {
+        if self.finished {
+            return self.device.get_inputs();
+        }
+        if self.frame == self.log.end_frame() {
+            if self.quit_at_end {
+                return Err(Error::Quit.into());
+            }
+            eprintln!("Replay finished after {} ms, the buttons are live", self.ms);
+            self.finished = true;
+            return self.device.get_inputs();
+        }
+        let change = self.log.changes.get(self.next_change).copied();
+        if let Some(change) = change.filter(|change| change.frame == self.frame) {
+            if self.step {
+                eprintln!(
+                    "Frame {} at {} ms: buttons {}, press a button to continue",
+                    change.frame,
+                    change.ms,
+                    format_buttons(change.inputs)
+                );
+                self.wait_for_press()?;
+            }
+            self.inputs = change.inputs;
+            self.next_change += 1;
+        } else {
+            // Still lets the device notice a request to quit
+            self.device.get_inputs()?;
+        }
+        self.frame += 1;
+        Ok(self.inputs)
+    }
+
+    fn set_led(&mut self, new_state: bool) {
+        self.device.set_led(new_state)
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.device.set_rgb_led(color)
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.ms += ms as u64;
+        if self.finished {
+            self.device.delay_ms(ms)
+        }
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.log.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        self.device.display()
+    }
+
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), D::Error> {
+        self.device.flush_display(area)
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        self.device.on_event(event)
+    }
+
+    fn take_settings(&mut self) -> Option<Settings> {
+        self.device.take_settings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::replay::{Change, Log, Recorder, Replay};
+    use embedded_graphics::geometry::Size;
+    use embedded_graphics::primitives::Rectangle;
+    use program::abstract_device::{AbstractDevice, Inputs};
+    use program::framebuffer::Framebuffer;
+    use program::game::Game;
+    use program::settings::{GameMode, Settings};
+
+    // Plays a fixed list of input changes and quits at the given frame
+    struct TestDevice {
+        display: Framebuffer,
+        script: Vec<(u64, Inputs)>,
+        frame: u64,
+        end: u64,
+        inputs: Inputs,
+    }
+
+    impl TestDevice {
+        fn new(script: Vec<(u64, Inputs)>, end: u64) -> Self {
+            TestDevice {
+                display: Framebuffer::new(Size::new(128, 32)).unwrap(),
+                script,
+                frame: 0,
+                end,
+                inputs: Inputs::default(),
+            }
+        }
+    }
+
+    impl AbstractDevice for TestDevice {
+        type Display = Framebuffer;
+        type Error = Error;
+
+        fn get_inputs(&mut self) -> Result<Inputs, Error> {
+            if self.frame == self.end {
+                return Err(Error::Quit);
+            }
+            if let Some(&(_, inputs)) = self.script.iter().find(|(frame, _)| *frame == self.frame) {
+                self.inputs = inputs;
+            }
+            self.frame += 1;
+            Ok(self.inputs)
+        }
+
+        fn set_led(&mut self, _new_state: bool) {}
+
+        fn delay_ms(&mut self, _ms: u32) {}
+
+        fn get_rng_seed(&mut self) -> u64 {
+            42
+        }
+
+        fn display(&mut self) -> &mut Self::Display {
+            &mut self.display
+        }
+
+        fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn press(button1_down: bool, button2_down: bool) -> Inputs {
+        Inputs {
+            button1_down,
+            button2_down,
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_recording() {
+        let settings = Settings {
+            mode: GameMode::Lives,
+            ..Settings::default()
+        };
+        let script = vec![
+            (5, press(true, false)),
+            (20, press(false, false)),
+            (400, press(true, false)),
+            (420, press(false, false)),
+            (440, press(false, true)),
+            (460, press(false, false)),
+        ];
+        let recorder = Recorder::new(TestDevice::new(script, 600), Vec::new(), settings);
+        let mut game = Game::with_settings(recorder, settings).unwrap();
+        assert!(matches!(game.run_game(), Err(Error::Quit)));
+        let recorder = game.into_device();
+        let recorded = recorder.device.display.clone();
+        let log = Log::parse(&String::from_utf8(recorder.into_log()).unwrap()).unwrap();
+
+        assert_eq!(log.seed, 42);
+        assert_eq!(log.settings, settings);
+        assert_eq!(log.changes.len(), 6);
+        assert_eq!(log.end, Some(600));
+        assert!(
+            log.changes[0]
+                == Change {
+                    frame: 5,
+                    ms: log.changes[0].ms,
+                    inputs: press(true, false)
+                }
+        );
+
+        // The replay quits at the same frame as the recorded session did
+        let replay = Replay::new(TestDevice::new(Vec::new(), u64::MAX), log, false, true);
+        let mut game = Game::with_settings(replay, settings).unwrap();
+        assert!(matches!(game.run_game(), Err(Error::Quit)));
+        let replayed = &game.into_device().device.display;
+        assert!(replayed.changed_area(&recorded).is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Log::parse("seed 1\n").is_err());
+        assert!(Log::parse("visual-memory replay 1\n").is_err());
+        assert!(Log::parse("visual-memory replay 1\nseed 1\n5 0 12\n").is_err());
+        assert!(Log::parse("visual-memory replay 1\nseed 1\n5 0 10\n3 0 00\n").is_err());
+        assert!(Log::parse("visual-memory replay 1\nseed 1\n5 0 end\n6 0 10\n").is_err());
+        let log = Log::parse("visual-memory replay 1\nseed 1\n# comment\n\n5 0 10\n").unwrap();
+        assert_eq!(log.changes.len(), 1);
+        assert_eq!(log.end_frame(), 6);
+    }
+}
@@ -0,0 +1,341 @@
+use embedded_graphics_simulator::{OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window};
+use crate::options::Config;
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888};
+use embedded_graphics::prelude::*;
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::GameControllerSubsystem;
+use std::thread;
+use std::time::Duration;
+use program::abstract_device::{AbstractDevice, Inputs};
+use crate::error::Error;
+
+// Height of the strip of on-screen buttons below the display, in display pixels
+const PANEL_HEIGHT: u32 = 16;
+// Width of the strip right of the display with the virtual RGB LED, in display pixels
+const LED_STRIP_WIDTH: u32 = 12;
+const LED_DIAMETER: u32 = 8;
+// Triggers are analog, count them as pressed when pulled past this point
+const TRIGGER_THRESHOLD: i16 = i16::MAX / 2;
+
+pub struct Device {
+    simulator_display: SimulatorDisplay<BinaryColor>,
+    // What the window shows: the display or the panel in the theme's colours, and the LED
+    window_display: SimulatorDisplay<Rgb888>,
+    // Off and on pixels
+    colors: (Rgb888, Rgb888),
+    rgb_led: Rgb888,
+    window: Window,
+    has_updated: bool,
+    keys: [Keycode; 2],
+    keys_down: [bool; 2],
+    panel: Option<Panel>,
+    controllers: Controllers,
+    inputs: Inputs,
+    seed: u64,
+}
+
+// The display with clickable buttons drawn below it
+struct Panel {
+    display: SimulatorDisplay<BinaryColor>,
+    buttons: [Rectangle; 2],
+    clicked: Option<usize>,
+}
+
+enum PadInput {
+    Button(Button),
+    Trigger(Axis),
+}
+
+struct Controllers {
+    subsystem: GameControllerSubsystem,
+    open: Vec<GameController>,
+    // Number of joysticks when `open` was filled, to notice controllers being plugged in
+    joysticks: u32,
+    mapping: [Vec<PadInput>; 2],
+}
+
+impl Device {
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let mut keys = [Keycode::Z, Keycode::X];
+        for (key, name) in keys.iter_mut().zip(&config.keys) {
+            *key = Keycode::from_name(name)
+                .ok_or_else(|| Error::Config(format!("unknown SDL key `{}`", name)))?;
+        }
+        // The theme is applied when drawing the window, the simulator's themes are only for
+        // monochrome displays
+        let output_settings = OutputSettingsBuilder::new()
+            .scale(config.scale)
+            .pixel_spacing(1)
+            .build();
+        let panel = config.on_screen_buttons.then(|| Panel::new(config.size));
+        let height = config.size.height + if panel.is_some() { PANEL_HEIGHT } else { 0 };
+        let window_size = Size::new(config.size.width + LED_STRIP_WIDTH, height);
+        Ok(Device {
+            simulator_display: SimulatorDisplay::new(config.size),
+            window_display: SimulatorDisplay::new(window_size),
+            colors: config.theme.colors(),
+            rgb_led: Rgb888::BLACK,
+            window: Window::new("Visual Memory", &output_settings),
+            has_updated: false,
+            keys,
+            keys_down: [false; 2],
+            panel,
+            controllers: Controllers::new(&config.pad_buttons)?,
+            inputs: Inputs::default(),
+            seed: config.seed,
+        })
+    }
+
+    fn update_window(&mut self) {
+        let display = match &mut self.panel {
+            Some(panel) => {
+                panel.draw(&self.simulator_display, self.inputs);
+                &panel.display
+            }
+            None => &self.simulator_display,
+        };
+        let (off, on) = self.colors;
+        let area = display.bounding_box();
+        let pixels = area.points().map(|point| {
+            let color = match display.get_pixel(point) {
+                BinaryColor::On => on,
+                BinaryColor::Off => off,
+            };
+            Pixel(point, color)
+        });
+        let Ok(()) = self.window_display.draw_iter(pixels);
+
+        // Ringed in the on colour, so it can be told apart from the background when off
+        let strip = Rectangle::new(
+            Point::new(area.size.width as i32, 0),
+            Size::new(LED_STRIP_WIDTH, area.size.height),
+        );
+        let Ok(()) = self.window_display.fill_solid(&strip, off);
+        let led_style = PrimitiveStyleBuilder::new()
+            .fill_color(self.rgb_led)
+            .stroke_color(on)
+            .stroke_width(1)
+            .build();
+        let top_left = strip.top_left + Point::new(2, 2);
+        let Ok(()) = Circle::new(top_left, LED_DIAMETER)
+            .into_styled(led_style)
+            .draw(&mut self.window_display);
+
+        self.window.update(&self.window_display);
+        self.has_updated = true;
+    }
+}
+
+impl Panel {
+    fn new(size: Size) -> Self {
+        let width = (size.width / 2).saturating_sub(4);
+        let top = size.height as i32 + 2;
+        let button_size = Size::new(width, PANEL_HEIGHT - 4);
+        Panel {
+            display: SimulatorDisplay::new(Size::new(size.width, size.height + PANEL_HEIGHT)),
+            buttons: [
+                Rectangle::new(Point::new(2, top), button_size),
+                Rectangle::new(Point::new(size.width as i32 - 2 - width as i32, top), button_size),
+            ],
+            clicked: None,
+        }
+    }
+
+    fn draw(&mut self, display: &SimulatorDisplay<BinaryColor>, inputs: Inputs) {
+        let display_area = display.bounding_box();
+        let pixels = display_area
+            .points()
+            .map(|point| Pixel(point, display.get_pixel(point)));
+        let Ok(()) = self.display.draw_iter(pixels);
+
+        let panel_area = Rectangle::new(
+            Point::new(0, display_area.size.height as i32),
+            Size::new(display_area.size.width, PANEL_HEIGHT),
+        );
+        let Ok(()) = self.display.fill_solid(&panel_area, BinaryColor::Off);
+        let Ok(()) = Line::new(
+            panel_area.top_left,
+            panel_area.top_left + Point::new(panel_area.size.width as i32 - 1, 0),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(&mut self.display);
+
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        let pressed = [inputs.button1_down, inputs.button2_down];
+        for ((button, pressed), label) in self.buttons.iter().zip(pressed).zip(["1", "2"]) {
+            let (style, text_color) = if pressed {
+                (PrimitiveStyle::with_fill(BinaryColor::On), BinaryColor::Off)
+            } else {
+                (PrimitiveStyle::with_stroke(BinaryColor::On, 1), BinaryColor::On)
+            };
+            let Ok(()) = button.into_styled(style).draw(&mut self.display);
+            let text_style = MonoTextStyle::new(&FONT_6X10, text_color);
+            let Ok(_) = Text::with_text_style(label, button.center(), text_style, centered)
+                .draw(&mut self.display);
+        }
+    }
+
+    fn button_at(&self, point: Point) -> Option<usize> {
+        self.buttons.iter().position(|button| button.contains(point))
+    }
+}
+
+impl Controllers {
+    fn new(mapping_names: &[Vec<String>; 2]) -> Result<Self, Error> {
+        let subsystem = sdl2::init()
+            .and_then(|sdl| sdl.game_controller())
+            .map_err(Error::Sdl)?;
+        let mut mapping: [Vec<PadInput>; 2] = Default::default();
+        for (inputs, names) in mapping.iter_mut().zip(mapping_names) {
+            for name in names {
+                let input = match name.as_str() {
+                    "lefttrigger" => PadInput::Trigger(Axis::TriggerLeft),
+                    "righttrigger" => PadInput::Trigger(Axis::TriggerRight),
+                    name => Button::from_string(name).map(PadInput::Button).ok_or_else(|| {
+                        Error::Config(format!("unknown game controller button `{}`", name))
+                    })?,
+                };
+                inputs.push(input);
+            }
+        }
+        Ok(Controllers {
+            subsystem,
+            open: Vec::new(),
+            joysticks: 0,
+            mapping,
+        })
+    }
+
+    // Opens every connected controller again whenever one is plugged in or out
+    fn refresh(&mut self) {
+        let joysticks = self.subsystem.num_joysticks().unwrap_or(0);
+        if joysticks == self.joysticks {
+            return;
+        }
+        self.joysticks = joysticks;
+        self.open = (0..joysticks)
+            .filter(|&index| self.subsystem.is_game_controller(index))
+            .filter_map(|index| self.subsystem.open(index).ok())
+            .collect();
+    }
+
+    fn is_down(&self, button: usize) -> bool {
+        self.open.iter().any(|controller| {
+            self.mapping[button].iter().any(|input| match *input {
+                PadInput::Button(button) => controller.button(button),
+                PadInput::Trigger(axis) => controller.axis(axis) > TRIGGER_THRESHOLD,
+            })
+        })
+    }
+}
+
+impl AbstractDevice for Device {
+    type Display = SimulatorDisplay<BinaryColor>;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Error> {
+        if !self.has_updated {
+            self.update_window();
+        }
+        // Polling the window also has SDL update the controller states
+        for event in self.window.events() {
+            match event {
+                SimulatorEvent::KeyUp {
+                    keycode,
+                    keymod: _,
+                    repeat: _,
+                } => {
+                    if keycode == self.keys[0] {
+                        self.keys_down[0] = false;
+                    } else if keycode == self.keys[1] {
+                        self.keys_down[1] = false;
+                    }
+                }
+                SimulatorEvent::KeyDown {
+                    keycode,
+                    keymod: _,
+                    repeat: _,
+                } => {
+                    if keycode == self.keys[0] {
+                        self.keys_down[0] = true;
+                    } else if keycode == self.keys[1] {
+                        self.keys_down[1] = true;
+                    } else if keycode == Keycode::Escape {
+                        return Err(Error::Quit);
+                    }
+                }
+                SimulatorEvent::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    point,
+                } => {
+                    if let Some(panel) = &mut self.panel {
+                        panel.clicked = panel.button_at(point);
+                    }
+                }
+                SimulatorEvent::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    point: _,
+                } => {
+                    if let Some(panel) = &mut self.panel {
+                        panel.clicked = None;
+                    }
+                }
+                SimulatorEvent::Quit => {
+                    return Err(Error::Quit);
+                }
+                _ => {}
+            }
+        }
+        self.controllers.refresh();
+
+        let clicked = self.panel.as_ref().and_then(|panel| panel.clicked);
+        let inputs = Inputs {
+            button1_down: self.keys_down[0] || clicked == Some(0) || self.controllers.is_down(0),
+            button2_down: self.keys_down[1] || clicked == Some(1) || self.controllers.is_down(1),
+        };
+        if inputs != self.inputs {
+            self.inputs = inputs;
+            // Show the on-screen buttons being pressed, even when the game has nothing to redraw
+            if self.panel.is_some() {
+                self.update_window();
+            }
+        }
+        Ok(self.inputs)
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        // The game changes it between flushes too, while a sequence is shown
+        if color != self.rgb_led {
+            self.rgb_led = color;
+            self.update_window();
+        }
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        thread::sleep(Duration::from_millis(ms as u64))
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.simulator_display
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+        self.update_window();
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+use crate::error::Error;
+use crate::options::Config;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Colors};
+use crossterm::{cursor, execute, queue, style, terminal};
+use embedded_graphics::geometry::{OriginDimensions, Point};
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::primitives::Rectangle;
+use program::abstract_device::{AbstractDevice, Inputs};
+use program::framebuffer::Framebuffer;
+use std::io::{self, Stdout, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Most terminals only report key presses and auto-repeats, never releases. There a key counts as
+// held until this long after its last press, which is enough to push both buttons together.
+const HOLD_WITHOUT_RELEASE: Duration = Duration::from_millis(150);
+
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Glyphs {
+    // One cell per 1x2 pixels
+    HalfBlock,
+    // One cell per 2x4 pixels, for terminals too small for half blocks
+    Braille,
+}
+
+pub struct Device {
+    display: Framebuffer,
+    stdout: Stdout,
+    glyphs: Glyphs,
+    colors: Colors,
+    keys: [KeyCode; 2],
+    reports_release: bool,
+    // When each button was last pressed, or None while it is up
+    pressed_at: [Option<Instant>; 2],
+    seed: u64,
+    rgb_led: Rgb888,
+}
+
+impl Device {
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let mut keys = [KeyCode::Null; 2];
+        for (key, name) in keys.iter_mut().zip(&config.keys) {
+            *key = parse_key(name)
+                .ok_or_else(|| Error::Config(format!("unknown terminal key `{}`", name)))?;
+        }
+        let (off, on) = config.theme.colors();
+        let colors = Colors::new(to_color(on), to_color(off));
+
+        // Made before the terminal is changed, so dropping it on an error below restores it
+        let mut device = Device {
+            display: Framebuffer::new(config.size)?,
+            stdout: io::stdout(),
+            glyphs: config.glyphs,
+            colors,
+            keys,
+            reports_release: false,
+            pressed_at: [None; 2],
+            seed: config.seed,
+            rgb_led: Rgb888::BLACK,
+        };
+        terminal::enable_raw_mode()?;
+        execute!(device.stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                device.stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+            device.reports_release = true;
+        }
+        device.draw()?;
+        Ok(device)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), Error> {
+        let code = match key.code {
+            KeyCode::Char(character) => KeyCode::Char(character.to_ascii_lowercase()),
+            code => code,
+        };
+        let button = match code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(Error::Quit)
+            }
+            code if code == self.keys[0] => 0,
+            code if code == self.keys[1] => 1,
+            KeyCode::Esc => return Err(Error::Quit),
+            _ => return Ok(()),
+        };
+        self.pressed_at[button] = match key.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => Some(Instant::now()),
+            KeyEventKind::Release => None,
+        };
+        Ok(())
+    }
+
+    fn is_down(&self, button: usize) -> bool {
+        match self.pressed_at[button] {
+            Some(_) if self.reports_release => true,
+            Some(pressed_at) => pressed_at.elapsed() < HOLD_WITHOUT_RELEASE,
+            None => false,
+        }
+    }
+
+    fn draw(&mut self) -> Result<(), Error> {
+        let lines = match self.glyphs {
+            Glyphs::HalfBlock => render_half_blocks(&self.display),
+            Glyphs::Braille => render_braille(&self.display),
+        };
+        let width = lines.first().map_or(0, |line| line.chars().count());
+        queue!(self.stdout, cursor::MoveTo(0, 0), style::ResetColor)?;
+        queue!(self.stdout, style::Print(format!("┌{}┐", "─".repeat(width))))?;
+        for (row, line) in lines.iter().enumerate() {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, row as u16 + 1),
+                style::Print("│"),
+                style::SetColors(self.colors),
+                style::Print(line),
+                style::ResetColor,
+                style::Print("│")
+            )?;
+        }
+        self.queue_rgb_led(width)?;
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, lines.len() as u16 + 1),
+            style::Print(format!("└{}┘", "─".repeat(width))),
+            cursor::MoveTo(0, lines.len() as u16 + 2),
+            style::Print("Esc: quit")
+        )?;
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    // The virtual RGB LED, right of the display's first row. A hollow circle while it's off.
+    fn queue_rgb_led(&mut self, width: usize) -> Result<(), Error> {
+        queue!(self.stdout, cursor::MoveTo(width as u16 + 3, 1))?;
+        if self.rgb_led == Rgb888::BLACK {
+            queue!(self.stdout, style::Print("○"))?;
+        } else {
+            queue!(
+                self.stdout,
+                style::SetForegroundColor(to_color(self.rgb_led)),
+                style::Print("●"),
+                style::ResetColor
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        if self.reports_release {
+            let _ = execute!(self.stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl AbstractDevice for Device {
+    type Display = Framebuffer;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Error> {
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) => self.handle_key(key)?,
+                Event::Resize(_, _) => {
+                    execute!(self.stdout, terminal::Clear(terminal::ClearType::All))?;
+                    self.draw()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(Inputs {
+            button1_down: self.is_down(0),
+            button2_down: self.is_down(1),
+        })
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.rgb_led = color;
+        // Can't fail the game from here, the next flush reports a broken terminal
+        let _ = self.draw();
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        thread::sleep(Duration::from_millis(ms as u64))
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+        self.draw()
+    }
+}
+
+// A single character, or the name of a special key
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut characters = name.chars();
+    if let (Some(character), None) = (characters.next(), characters.next()) {
+        return Some(KeyCode::Char(character.to_ascii_lowercase()));
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn to_color(color: Rgb888) -> Color {
+    Color::Rgb {
+        r: color.r(),
+        g: color.g(),
+        b: color.b(),
+    }
+}
+
+fn render_half_blocks(display: &Framebuffer) -> Vec<String> {
+    let size = display.size();
+    (0..size.height as i32)
+        .step_by(2)
+        .map(|y| {
+            (0..size.width as i32)
+                .map(|x| {
+                    let top = display.get_pixel(Point::new(x, y));
+                    let bottom = display.get_pixel(Point::new(x, y + 1));
+                    match (top, bottom) {
+                        (false, false) => ' ',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (true, true) => '█',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn render_braille(display: &Framebuffer) -> Vec<String> {
+    // Bit of each dot in a braille cell, indexed by [row][column]
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let size = display.size();
+    (0..size.height as i32)
+        .step_by(4)
+        .map(|y| {
+            (0..size.width as i32)
+                .step_by(2)
+                .map(|x| {
+                    let mut bits = 0;
+                    for (row, columns) in DOTS.iter().enumerate() {
+                        for (column, bit) in columns.iter().enumerate() {
+                            let point = Point::new(x + column as i32, y + row as i32);
+                            if display.get_pixel(point) {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terminal::{render_braille, render_half_blocks};
+    use embedded_graphics::geometry::{Point, Size};
+    use program::framebuffer::Framebuffer;
+
+    #[test]
+    fn test_render() {
+        let mut display = Framebuffer::new(Size::new(4, 4)).unwrap();
+        display.set_pixel(Point::new(0, 0), true);
+        display.set_pixel(Point::new(1, 1), true);
+        display.set_pixel(Point::new(2, 0), true);
+        display.set_pixel(Point::new(2, 1), true);
+        display.set_pixel(Point::new(3, 3), true);
+
+        assert_eq!(render_half_blocks(&display), ["▀▄█ ", "   ▄"]);
+        assert_eq!(render_braille(&display), ["⠑⢃"]);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::options::Theme;
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888, RgbColor};
+use embedded_graphics::prelude::{Dimensions, DrawTarget};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::settings::Settings;
+use program::framebuffer::Framebuffer;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+// How long the last frame stays up when the session ends right after it
+const MIN_LAST_FRAME_MS: u64 = 1000;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+    Gif,
+    Apng,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Format::Gif),
+            "png" | "apng" => Some(Format::Apng),
+            _ => None,
+        }
+    }
+}
+
+// Passes everything through to another device and keeps each flushed frame, which it encodes into
+// an animation when dropped. Frames are timed by the delays the game asks for, so a replay that
+// skips them still gives a video in real time.
+pub struct Video<D> {
+    device: D,
+    path: PathBuf,
+    theme: Theme,
+    scale: u32,
+    // The game draws into this device, which draws into both the wrapped one and this frame
+    frame: Framebuffer,
+    frames: Vec<(u64, Framebuffer)>,
+    ms: u64,
+}
+
+impl<D: AbstractDevice> Video<D> {
+    pub fn new(mut device: D, path: PathBuf, theme: Theme, scale: u32) -> Result<Self, D::Error> {
+        let size = device.display().bounding_box().size;
+        Ok(Video {
+            device,
+            path,
+            theme,
+            scale,
+            frame: Framebuffer::new(size)?,
+            frames: Vec::new(),
+            ms: 0,
+        })
+    }
+}
+
+impl<D> Video<D> {
+    // Each frame with how long it was shown, in ms
+    fn timed_frames(&self) -> impl Iterator<Item = (&Framebuffer, u64)> {
+        let ends = self
+            .frames
+            .iter()
+            .skip(1)
+            .map(|(ms, _)| *ms)
+            .chain([self.ms.max(self.frames.last().map_or(0, |(ms, _)| *ms) + MIN_LAST_FRAME_MS)]);
+        self.frames
+            .iter()
+            .zip(ends)
+            .map(|((start, frame), end)| (frame, end - start))
+    }
+
+    // One palette index per pixel, 0 for off and 1 for on, scaled up
+    fn indices(&self, frame: &Framebuffer) -> Vec<u8> {
+        let size = frame.size();
+        let mut indices = Vec::with_capacity((size.width * size.height * self.scale.pow(2)) as usize);
+        for y in 0..size.height * self.scale {
+            for x in 0..size.width * self.scale {
+                let point = Point::new((x / self.scale) as i32, (y / self.scale) as i32);
+                indices.push(frame.get_pixel(point) as u8);
+            }
+        }
+        indices
+    }
+
+    fn palette(&self) -> [u8; 6] {
+        let (off, on) = self.theme.colors();
+        [off.r(), off.g(), off.b(), on.r(), on.g(), on.b()]
+    }
+
+    fn write_gif(&self, file: File) -> Result<(), gif::EncodingError> {
+        let size = self.frame.size() * self.scale;
+        let (width, height) = (size.width as u16, size.height as u16);
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &self.palette())?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for (frame, ms) in self.timed_frames() {
+            let mut gif_frame = gif::Frame::from_indexed_pixels(width, height, self.indices(frame), None);
+            // GIF delays are in hundredths of a second
+            gif_frame.delay = (ms / 10).min(u16::MAX as u64) as u16;
+            encoder.write_frame(&gif_frame)?;
+        }
+        Ok(())
+    }
+
+    fn write_apng(&self, file: File) -> Result<(), png::EncodingError> {
+        let size = self.frame.size() * self.scale;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), size.width, size.height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(Cow::Owned(self.palette().to_vec()));
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+        for (frame, ms) in self.timed_frames() {
+            writer.set_frame_delay(ms.min(u16::MAX as u64) as u16, 1000)?;
+            writer.write_image_data(&self.indices(frame))?;
+        }
+        writer.finish()
+    }
+
+    fn write(&self) -> Result<(), String> {
+        let file = File::create(&self.path).map_err(|error| error.to_string())?;
+        match Format::of(&self.path) {
+            Some(Format::Gif) => self.write_gif(file).map_err(|error| error.to_string()),
+            Some(Format::Apng) => self.write_apng(file).map_err(|error| error.to_string()),
+            None => Err("unknown format".into()),
+        }
+    }
+}
+
+impl<D> Drop for Video<D> {
+    fn drop(&mut self) {
+        if self.frames.is_empty() {
+            return;
+        }
+        if let Err(error) = self.write() {
+            eprintln!("Could not write {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+impl<D: AbstractDevice> OriginDimensions for Video<D> {
+    fn size(&self) -> Size {
+        self.frame.size()
+    }
+}
+
+impl<D: AbstractDevice> DrawTarget for Video<D> {
+    type Color = BinaryColor;
+    type Error = <D::Display as DrawTarget>::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let frame = &mut self.frame;
+        let pixels = pixels.into_iter().inspect(|&Pixel(point, color)| {
+            frame.set_pixel(point, color.is_on());
+        });
+        self.device.display().draw_iter(pixels)
+    }
+}
+
+impl<D: AbstractDevice> AbstractDevice for Video<D> {
+    type Display = Self;
+    type Error = D::Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, D::Error> {
+        self.device.get_inputs()
+    }
+
+    fn set_led(&mut self, new_state: bool) {
+        self.device.set_led(new_state)
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.device.set_rgb_led(color)
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.ms += ms as u64;
+        self.device.delay_ms(ms)
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.device.get_rng_seed()
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        self
+    }
+
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), D::Error> {
+        // Frames flushed at the same moment are never seen, only the last of them
+        if self.frames.last().is_some_and(|(ms, _)| *ms == self.ms) {
+            self.frames.pop();
+        }
+        self.frames.push((self.ms, self.frame.clone()));
+        self.device.flush_display(area)
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        self.device.on_event(event)
+    }
+
+    fn take_settings(&mut self) -> Option<Settings> {
+        self.device.take_settings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::headless;
+    use crate::options::Theme;
+    use crate::video::Video;
+    use embedded_graphics::geometry::{Point, Size};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::DrawTarget;
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_graphics::Pixel;
+    use program::abstract_device::AbstractDevice;
+
+    #[test]
+    fn test_frames() -> Result<(), Error> {
+        let device = headless::Device::new(Size::new(4, 2), 0)?;
+        let path = std::env::temp_dir().join("visual-memory-test-frames.gif");
+        let mut video = Video::new(device, path.clone(), Theme::Default, 2)?;
+        video.display().draw_iter([Pixel(Point::new(1, 0), BinaryColor::On)])?;
+        video.flush_display(Rectangle::new(Point::new(1, 0), Size::new(1, 1)))?;
+        video.delay_ms(500);
+        video.display().draw_iter([Pixel(Point::new(2, 1), BinaryColor::On)])?;
+        video.flush_display(Rectangle::new(Point::new(2, 1), Size::new(1, 1)))?;
+        video.display().draw_iter([Pixel(Point::new(3, 1), BinaryColor::On)])?;
+        video.flush_display(Rectangle::new(Point::new(3, 1), Size::new(1, 1)))?;
+        video.delay_ms(1500);
+
+        let frames: Vec<u64> = video.timed_frames().map(|(_, ms)| ms).collect();
+        assert_eq!(frames, [500, 1500]);
+        let (last, _) = video.timed_frames().last().unwrap();
+        assert!(last.get_pixel(Point::new(2, 1)) && last.get_pixel(Point::new(3, 1)));
+        assert_eq!(video.indices(last), [
+            0, 0, 1, 1, 0, 0, 0, 0,
+            0, 0, 1, 1, 0, 0, 0, 0,
+            0, 0, 0, 0, 1, 1, 1, 1,
+            0, 0, 0, 0, 1, 1, 1, 1,
+        ]);
+
+        drop(video);
+        let file = std::fs::read(&path)?;
+        std::fs::remove_file(&path)?;
+        assert!(file.starts_with(b"GIF89a"));
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
-use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888};
 use embedded_graphics::prelude::DrawTarget;
+use embedded_graphics::primitives::Rectangle;
+use crate::framebuffer::TooLarge;
+use crate::settings::Settings;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Inputs {
     pub button1_down: bool,
     pub button2_down: bool,
@@ -15,14 +19,46 @@ impl Default for Inputs {
     }
 }
 
+// What happens during a game, for devices that keep statistics or logs
+#[derive(Copy, Clone, PartialEq)]
+pub enum GameEvent<'a> {
+    // `seed` is the RNG's state as the game starts, a session seeded with it plays the same sequences
+    GameStarted { settings: Settings, seed: u64 },
+    SequenceShown { level: usize, sequence: &'a [bool] },
+    // The sequence was hidden and the player can start repeating it
+    SequenceHidden,
+    Input { index: usize, value: bool, correct: bool },
+    GameOver { score: f32 },
+}
+
 pub trait AbstractDevice {
     type Display: DrawTarget<Color=BinaryColor>;
-    type Error: From<<Self::Display as DrawTarget>::Error> + From<core::fmt::Error>;
+    // TooLarge when the display is larger than the frame the game draws into, see framebuffer.rs
+    type Error: From<<Self::Display as DrawTarget>::Error> + From<core::fmt::Error> + From<TooLarge>;
     fn get_inputs(&mut self) -> Result<Inputs, Self::Error>;
     fn set_led(&mut self, new_state: bool);
     fn delay_ms(&mut self, ms: u32);
     fn get_rng_seed(&mut self) -> u64;
 
     fn display(&mut self) -> &mut Self::Display;
-    fn flush_display(&mut self) -> Result<(), Self::Error>;
+    // `area` holds every pixel the game drew into display() since the last flush, so devices that
+    // send frames on can send just that
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), Self::Error>;
+
+    fn on_event(&mut self, _event: GameEvent) {}
+
+    // Settings changed on the device itself, for example over a serial console. The game picks
+    // them up in the menu, so they apply from the next game.
+    fn take_settings(&mut self) -> Option<Settings> {
+        None
+    }
+
+    // How full the battery is in percent, for devices that run from one and can measure it. The
+    // menu shows it and warns when it runs low, and the HUD shows it from then on.
+    fn battery_level(&mut self) -> Option<u8> {
+        None
+    }
+
+    // An RGB LED or a strip of them, for devices that have one. Black turns it off.
+    fn set_rgb_led(&mut self, _color: Rgb888) {}
 }
\ No newline at end of file
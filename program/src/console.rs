@@ -0,0 +1,380 @@
+use crate::abstract_device::GameEvent;
+use crate::settings::{Difficulty, GameMode, Settings};
+use crate::stats::Stats;
+use core::fmt::{self, Write};
+use core::str::FromStr;
+
+// Longer lines are rejected when they end, instead of being run cut short
+pub const MAX_LINE: usize = 64;
+
+const HELP: &str = "help                               this list\r\n\
+scores                             best score for each mode and difficulty\r\n\
+stats                              games, scores and inputs so far\r\n\
+settings                           mode and difficulty\r\n\
+set mode <classic|lives>           change the mode from the next game\r\n\
+set difficulty <easy|normal|hard>  change the difficulty from the next game\r\n\
+events <on|off>                    print game events as they happen\r\n\
+bootloader                         reboot into the UF2 bootloader\r\n";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Command {
+    Help,
+    Scores,
+    Stats,
+    Settings,
+    SetMode(GameMode),
+    SetDifficulty(Difficulty),
+    Events(bool),
+    Bootloader,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ParseCommandError {
+    Unknown,
+    MissingArgument,
+    InvalidArgument,
+    TooLong,
+}
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseCommandError::Unknown => "unknown command, try help",
+            ParseCommandError::MissingArgument => "missing argument",
+            ParseCommandError::InvalidArgument => "invalid argument",
+            ParseCommandError::TooLong => "line too long",
+        })
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseCommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().ok_or(ParseCommandError::Unknown)?;
+        let mut argument = || words.next().ok_or(ParseCommandError::MissingArgument);
+        let is = |name: &str| command.eq_ignore_ascii_case(name);
+        let command = if is("help") {
+            Command::Help
+        } else if is("scores") {
+            Command::Scores
+        } else if is("stats") {
+            Command::Stats
+        } else if is("settings") {
+            Command::Settings
+        } else if is("set") {
+            let setting = argument()?;
+            let value = argument()?;
+            let invalid = |_| ParseCommandError::InvalidArgument;
+            if setting.eq_ignore_ascii_case("mode") {
+                Command::SetMode(value.parse().map_err(invalid)?)
+            } else if setting.eq_ignore_ascii_case("difficulty") {
+                Command::SetDifficulty(value.parse().map_err(invalid)?)
+            } else {
+                return Err(ParseCommandError::InvalidArgument);
+            }
+        } else if is("events") {
+            let value = argument()?;
+            if value.eq_ignore_ascii_case("on") {
+                Command::Events(true)
+            } else if value.eq_ignore_ascii_case("off") {
+                Command::Events(false)
+            } else {
+                return Err(ParseCommandError::InvalidArgument);
+            }
+        } else if is("bootloader") {
+            Command::Bootloader
+        } else {
+            return Err(ParseCommandError::Unknown);
+        };
+        if words.next().is_some() {
+            return Err(ParseCommandError::InvalidArgument);
+        }
+        Ok(command)
+    }
+}
+
+// What the console can't do by itself and leaves to the device
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Request {
+    Bootloader,
+}
+
+// A line based text console for a serial port. It echoes what is typed, runs each line as a
+// command, keeps the stats the commands print and can print game events as they happen.
+pub struct Console {
+    line: [u8; MAX_LINE],
+    length: usize,
+    overflowed: bool,
+    settings: Settings,
+    // Settings changed from the console that the game hasn't picked up yet
+    new_settings: Option<Settings>,
+    events: bool,
+    stats: Stats,
+}
+
+impl Console {
+    pub fn new(settings: Settings) -> Self {
+        Console {
+            line: [0; MAX_LINE],
+            length: 0,
+            overflowed: false,
+            settings,
+            new_settings: None,
+            events: false,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn take_settings(&mut self) -> Option<Settings> {
+        self.new_settings.take()
+    }
+
+    // Handles one byte from the host, writing anything to show to out
+    pub fn receive<W: Write>(&mut self, byte: u8, out: &mut W) -> Result<Option<Request>, fmt::Error> {
+        match byte {
+            // Terminals send either or both at the end of a line, blank lines are ignored
+            b'\r' | b'\n' => {
+                let line = &self.line[..self.length];
+                if line.iter().all(u8::is_ascii_whitespace) && !self.overflowed {
+                    self.length = 0;
+                    return Ok(None);
+                }
+                out.write_str("\r\n")?;
+                let command = match self.overflowed {
+                    // Only printable ASCII is kept, so the line is always valid UTF-8
+                    false => core::str::from_utf8(line).unwrap_or_default().parse(),
+                    true => Err(ParseCommandError::TooLong),
+                };
+                self.length = 0;
+                self.overflowed = false;
+                match command {
+                    Ok(command) => self.run(command, out),
+                    Err(error) => {
+                        write!(out, "error: {}\r\n", error)?;
+                        Ok(None)
+                    }
+                }
+            }
+            // Backspace and delete
+            0x08 | 0x7f => {
+                if self.length > 0 && !self.overflowed {
+                    self.length -= 1;
+                    out.write_str("\x08 \x08")?;
+                }
+                Ok(None)
+            }
+            b' '..=b'~' => {
+                if self.length < MAX_LINE {
+                    self.line[self.length] = byte;
+                    self.length += 1;
+                } else {
+                    self.overflowed = true;
+                }
+                out.write_char(byte as char)?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn on_event<W: Write>(&mut self, event: GameEvent, out: &mut W) -> fmt::Result {
+        self.stats.record(event);
+        if let GameEvent::GameStarted { settings, .. } = event {
+            self.settings = settings;
+        }
+        if self.events {
+            write_event(event, out)?;
+        }
+        Ok(())
+    }
+
+    fn run<W: Write>(&mut self, command: Command, out: &mut W) -> Result<Option<Request>, fmt::Error> {
+        match command {
+            Command::Help => out.write_str(HELP)?,
+            Command::Scores => {
+                if self.stats.games() == 0 {
+                    out.write_str("no games yet\r\n")?;
+                }
+                for (settings, stats) in self.stats.played() {
+                    write_settings(settings, out)?;
+                    write!(out, ": {:.2}\r\n", stats.best_score)?;
+                }
+            }
+            Command::Stats => {
+                write!(
+                    out,
+                    "{} games, {} of {} inputs correct\r\n",
+                    self.stats.games(),
+                    self.stats.correct_inputs,
+                    self.stats.inputs
+                )?;
+                for (settings, stats) in self.stats.played() {
+                    write_settings(settings, out)?;
+                    write!(
+                        out,
+                        ": {} games, mean {:.2}, best {:.2}\r\n",
+                        stats.games,
+                        stats.mean_score(),
+                        stats.best_score
+                    )?;
+                }
+            }
+            Command::Settings => {
+                write_settings(self.settings, out)?;
+                out.write_str("\r\n")?;
+            }
+            Command::SetMode(mode) => self.set(Settings { mode, ..self.settings }, out)?,
+            Command::SetDifficulty(difficulty) => {
+                self.set(Settings { difficulty, ..self.settings }, out)?
+            }
+            Command::Events(events) => {
+                self.events = events;
+                write!(out, "events {}\r\n", if events { "on" } else { "off" })?;
+            }
+            Command::Bootloader => {
+                out.write_str("rebooting into the bootloader\r\n")?;
+                return Ok(Some(Request::Bootloader));
+            }
+        }
+        Ok(None)
+    }
+
+    fn set<W: Write>(&mut self, settings: Settings, out: &mut W) -> fmt::Result {
+        self.settings = settings;
+        self.new_settings = Some(settings);
+        write_settings(settings, out)?;
+        out.write_str(" from the next game\r\n")
+    }
+}
+
+fn write_settings<W: Write>(settings: Settings, out: &mut W) -> fmt::Result {
+    write!(out, "{} {}", settings.mode.name(), settings.difficulty.name())
+}
+
+// One line per event, buttons as digits like the desktop history: "112" is button 1, 1, 2
+pub fn write_event<W: Write>(event: GameEvent, out: &mut W) -> fmt::Result {
+    let digit = |value: bool| if value { '2' } else { '1' };
+    out.write_str("event ")?;
+    match event {
+        GameEvent::GameStarted { settings, .. } => {
+            out.write_str("started ")?;
+            write_settings(settings, out)?;
+        }
+        GameEvent::SequenceShown { level, sequence } => {
+            write!(out, "shown {} ", level)?;
+            for &value in sequence {
+                out.write_char(digit(value))?;
+            }
+        }
+        GameEvent::SequenceHidden => out.write_str("hidden")?,
+        GameEvent::Input { index, value, correct } => {
+            let result = if correct { "correct" } else { "wrong" };
+            write!(out, "input {} {} {}", index, digit(value), result)?;
+        }
+        GameEvent::GameOver { score } => write!(out, "over {:.2}", score)?,
+    }
+    out.write_str("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use crate::abstract_device::GameEvent;
+    use crate::console::{Command, Console, ParseCommandError, Request, MAX_LINE};
+    use crate::settings::{Difficulty, GameMode, Settings};
+    use std::string::String;
+
+    fn type_line(console: &mut Console, line: &str) -> (String, Option<Request>) {
+        let mut out = String::new();
+        let mut request = None;
+        for byte in line.bytes().chain(*b"\r\n") {
+            request = request.or(console.receive(byte, &mut out).unwrap());
+        }
+        (out, request)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("help".parse(), Ok(Command::Help));
+        assert_eq!("  STATS ".parse(), Ok(Command::Stats));
+        assert_eq!("set mode lives".parse(), Ok(Command::SetMode(GameMode::Lives)));
+        assert_eq!("set difficulty Hard".parse(), Ok(Command::SetDifficulty(Difficulty::Hard)));
+        assert_eq!("events on".parse(), Ok(Command::Events(true)));
+        assert_eq!("bootloader".parse(), Ok(Command::Bootloader));
+
+        assert_eq!("reboot".parse::<Command>(), Err(ParseCommandError::Unknown));
+        assert_eq!("set mode".parse::<Command>(), Err(ParseCommandError::MissingArgument));
+        assert_eq!("set mode fast".parse::<Command>(), Err(ParseCommandError::InvalidArgument));
+        assert_eq!("set speed fast".parse::<Command>(), Err(ParseCommandError::InvalidArgument));
+        assert_eq!("events maybe".parse::<Command>(), Err(ParseCommandError::InvalidArgument));
+        assert_eq!("help me".parse::<Command>(), Err(ParseCommandError::InvalidArgument));
+    }
+
+    #[test]
+    fn test_line_editing() {
+        let mut console = Console::new(Settings::default());
+        // Typed characters are echoed and backspace removes them
+        let (out, _) = type_line(&mut console, "setx\x08tings");
+        assert_eq!(out, "setx\x08 \x08tings\r\nclassic normal\r\n");
+        // Blank lines, like the \n after \r, don't start a new line
+        assert_eq!(type_line(&mut console, "  ").0, "  ");
+
+        let long = "a".repeat(MAX_LINE + 1);
+        let (out, _) = type_line(&mut console, &long);
+        assert!(out.ends_with("\r\nerror: line too long\r\n"));
+        // The next line starts empty again
+        assert_eq!(type_line(&mut console, "settings").0, "settings\r\nclassic normal\r\n");
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut console = Console::new(Settings::default());
+        assert_eq!(type_line(&mut console, "scores").0, "scores\r\nno games yet\r\n");
+        assert_eq!(
+            type_line(&mut console, "set mode lives").0,
+            "set mode lives\r\nlives normal from the next game\r\n"
+        );
+        let lives = Settings {
+            mode: GameMode::Lives,
+            difficulty: Difficulty::Normal,
+        };
+        assert_eq!(console.take_settings(), Some(lives));
+        assert_eq!(console.take_settings(), None);
+
+        type_line(&mut console, "events on");
+        let mut out = String::new();
+        let events = [
+            GameEvent::GameStarted { settings: lives, seed: 0 },
+            GameEvent::SequenceShown { level: 1, sequence: &[false, false, true] },
+            GameEvent::SequenceHidden,
+            GameEvent::Input { index: 0, value: true, correct: false },
+            GameEvent::GameOver { score: 2.5 },
+        ];
+        for event in events {
+            console.on_event(event, &mut out).unwrap();
+        }
+        assert_eq!(
+            out,
+            "event started lives normal\r\n\
+            event shown 1 112\r\n\
+            event hidden\r\n\
+            event input 0 2 wrong\r\n\
+            event over 2.50\r\n"
+        );
+
+        assert_eq!(type_line(&mut console, "scores").0, "scores\r\nlives normal: 2.50\r\n");
+        assert_eq!(
+            type_line(&mut console, "stats").0,
+            "stats\r\n1 games, 0 of 1 inputs correct\r\nlives normal: 1 games, mean 2.50, best 2.50\r\n"
+        );
+        let (out, request) = type_line(&mut console, "bootloader");
+        assert_eq!(out, "bootloader\r\nrebooting into the bootloader\r\n");
+        assert_eq!(request, Some(Request::Bootloader));
+    }
+}
@@ -30,6 +30,7 @@ pub struct Debouncer<'a> {
 
 #[repr(u8)]
 #[derive(PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DebounceResult {
     NoChange,
     Pressed,
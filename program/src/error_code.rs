@@ -0,0 +1,85 @@
+// How long the LED is on and off for each blink of a code, and the pause before it repeats
+pub const BLINK_ON_MS: u32 = 200;
+pub const BLINK_OFF_MS: u32 = 300;
+pub const BLINK_PAUSE_MS: u32 = 1200;
+
+// What went wrong on a device, in a form that fits in a byte, the LED and a small screen. The
+// number is how often the LED blinks, and 0 is left for resets without a known error.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ErrorCode {
+    I2c = 1,
+    Display = 2,
+    Format = 3,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 3] = [ErrorCode::I2c, ErrorCode::Display, ErrorCode::Format];
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<ErrorCode> {
+        ErrorCode::ALL.into_iter().find(|error| error.code() == code)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::I2c => "I2C error",
+            ErrorCode::Display => "Display error",
+            ErrorCode::Format => "Format error",
+        }
+    }
+
+    // What the player can do about it, for the error screen
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorCode::I2c | ErrorCode::Display => "Check the display.",
+            ErrorCode::Format => "Please report this.",
+        }
+    }
+
+    // One round of the blink code as (LED on, ms) steps: a blink per number, then a pause
+    pub fn blink_pattern(self) -> impl Iterator<Item = (bool, u32)> {
+        (0..self.code())
+            .flat_map(|_| [(true, BLINK_ON_MS), (false, BLINK_OFF_MS)])
+            .chain([(false, BLINK_PAUSE_MS)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use crate::error_code::{ErrorCode, BLINK_OFF_MS, BLINK_ON_MS, BLINK_PAUSE_MS};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_codes() {
+        for error in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(error.code()), Some(error));
+        }
+        assert_eq!(ErrorCode::from_code(0), None);
+        assert_eq!(ErrorCode::from_code(4), None);
+    }
+
+    #[test]
+    fn test_blink_patterns() {
+        let pattern: Vec<_> = ErrorCode::Display.blink_pattern().collect();
+        assert_eq!(
+            pattern,
+            [
+                (true, BLINK_ON_MS),
+                (false, BLINK_OFF_MS),
+                (true, BLINK_ON_MS),
+                (false, BLINK_OFF_MS),
+                (false, BLINK_PAUSE_MS),
+            ]
+        );
+        // Every error can be told apart by counting blinks
+        let blinks: Vec<usize> = ErrorCode::ALL
+            .into_iter()
+            .map(|error| error.blink_pattern().filter(|&(on, _)| on).count())
+            .collect();
+        assert_eq!(blinks, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,168 @@
+use core::convert::Infallible;
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::DrawTarget;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+
+// Large enough for the biggest panels we drive (SSD1306/SH1106 128x64)
+pub const MAX_WIDTH: u32 = 128;
+pub const MAX_HEIGHT: u32 = 64;
+pub const PAGE_HEIGHT: u32 = 8;
+const BUFFER_SIZE: usize = (MAX_WIDTH * MAX_HEIGHT / PAGE_HEIGHT) as usize;
+
+// A frame larger than MAX_WIDTH x MAX_HEIGHT, which Framebuffer::new has no room for
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TooLarge(pub Size);
+
+// In-memory 1bpp frame, laid out like the SSD1306 GDDRAM: one byte per column per 8-pixel page,
+// least significant bit at the top. That lets devices push pages straight to the controller.
+#[derive(Clone)]
+pub struct Framebuffer {
+    buffer: [u8; BUFFER_SIZE],
+    size: Size,
+}
+
+impl Framebuffer {
+    pub fn new(size: Size) -> Result<Self, TooLarge> {
+        if size.width > MAX_WIDTH || size.height > MAX_HEIGHT {
+            return Err(TooLarge(size));
+        }
+        Ok(Self {
+            buffer: [0x00; BUFFER_SIZE],
+            size,
+        })
+    }
+
+    pub fn pages(&self) -> u32 {
+        self.size.height.div_ceil(PAGE_HEIGHT)
+    }
+
+    // Bytes of one page, one per column
+    pub fn page(&self, page: u32) -> &[u8] {
+        let start = (page * self.size.width) as usize;
+        &self.buffer[start..start + self.size.width as usize]
+    }
+
+    pub fn get_pixel(&self, point: Point) -> bool {
+        match self.index(point) {
+            Some((index, bit)) => self.buffer[index] & (1 << bit) != 0,
+            None => false,
+        }
+    }
+
+    pub fn set_pixel(&mut self, point: Point, on: bool) {
+        if let Some((index, bit)) = self.index(point) {
+            if on {
+                self.buffer[index] |= 1 << bit;
+            } else {
+                self.buffer[index] &= !(1 << bit);
+            }
+        }
+    }
+
+    // Smallest page-aligned area that differs from `other`, or None if both frames are identical
+    pub fn changed_area(&self, other: &Framebuffer) -> Option<Rectangle> {
+        debug_assert!(self.size == other.size);
+        let mut min_column = u32::MAX;
+        let mut max_column = 0;
+        let mut min_page = u32::MAX;
+        let mut max_page = 0;
+        for page in 0..self.pages() {
+            let columns = self.page(page).iter().zip(other.page(page));
+            for (column, (a, b)) in columns.enumerate() {
+                if a != b {
+                    min_column = min_column.min(column as u32);
+                    max_column = max_column.max(column as u32);
+                    min_page = min_page.min(page);
+                    max_page = max_page.max(page);
+                }
+            }
+        }
+        if min_column == u32::MAX {
+            return None;
+        }
+        let top = min_page * PAGE_HEIGHT;
+        let bottom = ((max_page + 1) * PAGE_HEIGHT).min(self.size.height);
+        Some(Rectangle::new(
+            Point::new(min_column as i32, top as i32),
+            Size::new(max_column - min_column + 1, bottom - top),
+        ))
+    }
+
+    fn index(&self, point: Point) -> Option<(usize, u32)> {
+        if point.x < 0
+            || point.y < 0
+            || point.x as u32 >= self.size.width
+            || point.y as u32 >= self.size.height
+        {
+            return None;
+        }
+        let (x, y) = (point.x as u32, point.y as u32);
+        Some((((y / PAGE_HEIGHT) * self.size.width + x) as usize, y % PAGE_HEIGHT))
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.set_pixel(point, color.is_on());
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let fill = if color.is_on() { 0xff } else { 0x00 };
+        self.buffer.fill(fill);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framebuffer::{Framebuffer, TooLarge};
+    use embedded_graphics::geometry::{Point, Size};
+    use embedded_graphics::primitives::Rectangle;
+
+    #[test]
+    fn test_changed_area() {
+        let mut shown = Framebuffer::new(Size::new(128, 32)).unwrap();
+        let mut frame = shown.clone();
+        assert!(frame.changed_area(&shown).is_none());
+
+        frame.set_pixel(Point::new(10, 12), true);
+        frame.set_pixel(Point::new(20, 9), true);
+        assert!(
+            frame.changed_area(&shown) == Some(Rectangle::new(Point::new(10, 8), Size::new(11, 8)))
+        );
+
+        frame.set_pixel(Point::new(0, 31), true);
+        assert!(
+            frame.changed_area(&shown) == Some(Rectangle::new(Point::new(0, 8), Size::new(21, 24)))
+        );
+
+        shown = frame.clone();
+        assert!(frame.changed_area(&shown).is_none());
+    }
+
+    #[test]
+    fn test_too_large() {
+        assert!(Framebuffer::new(Size::new(128, 64)).is_ok());
+        let size = Size::new(129, 64);
+        assert!(Framebuffer::new(size).err() == Some(TooLarge(size)));
+        let size = Size::new(128, 65);
+        assert!(Framebuffer::new(size).err() == Some(TooLarge(size)));
+    }
+}
@@ -1,26 +1,65 @@
 use crate::debouncing::{DebounceResult, Debouncer};
-use core::mem::MaybeUninit;
-// use defmt::*;
-use crate::abstract_device::AbstractDevice;
+use crate::error_code::ErrorCode;
+use crate::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use crate::framebuffer::Framebuffer;
+use crate::settings::{GameMode, Settings};
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::Point;
+use embedded_graphics::image::{Image, ImageRaw};
 use embedded_graphics::mono_font::ascii::*;
 use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
-use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888, RgbColor};
 use embedded_graphics::prelude::{Dimensions, Primitive, Size};
-use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
-use embedded_graphics::text::{Baseline, Text};
-use embedded_graphics::Drawable;
-use fixed_slice_vec::FixedSliceVec;
+use embedded_graphics::primitives::{Line, PointsIter, PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use embedded_graphics::{Drawable, Pixel};
 
 const FONT_WIDTH: u32 = 6;
 const FONT_HEIGHT: u32 = 10;
 const BLOCK_GROUPING_EXTRA_SPACING: u32 = 2;
 const BLOCK_LINE_HEIGHT: u32 = 2;
 const BLOCK_SPACE: u32 = 2;
+// Rows shrink to fit long sequences below the HUD, but never so far that the two symbols look alike
+const MIN_BLOCK_ROW_HEIGHT: u32 = 4;
+// The HUD takes exactly one display page: a line of 4x6 text and a separator
+const HUD_HEIGHT: u32 = 8;
+const HUD_FONT_WIDTH: u32 = 4;
+const HEART_WIDTH: u32 = 5;
+#[rustfmt::skip]
+const HEART: [u8; 4] = [
+    0b01010000,
+    0b11111000,
+    0b01110000,
+    0b00100000,
+];
+// The battery icon in the menu's top right corner, and in the HUD while the battery is low, with a
+// nub on the right
+const BATTERY_WIDTH: u32 = 7;
+const BATTERY_HEIGHT: u32 = 5;
+// Warned about once on the menu when the battery drops to this, well before the Pico browns out
+const LOW_BATTERY_PERCENT: u8 = 10;
+const LOW_BATTERY_NOTICE: &str = "Battery low!\nCharge it soon.";
+// The RGB LED's colour for each symbol while a sequence is shown, and for the answers
+const SYMBOL_COLORS: [Rgb888; 2] = [Rgb888::BLUE, Rgb888::YELLOW];
+const CORRECT_COLOR: Rgb888 = Rgb888::GREEN;
+const INCORRECT_COLOR: Rgb888 = Rgb888::RED;
+// Run through while "Good! Next:" is shown, the next sequence's colours follow
+const LEVEL_UP_COLORS: [Rgb888; 4] = [Rgb888::CYAN, Rgb888::BLUE, Rgb888::MAGENTA, Rgb888::WHITE];
+const LEVEL_UP_MS: u32 = 400;
+// Skipped flushes take no time, so wait roughly as long as a full I2C flush would have. That keeps
+// the debouncer sampling at the rate it was tuned for.
+const IDLE_POLL_MS: u32 = 10;
+
+// A trace of the game for probe-connected devices, with the `defmt` feature
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::$level!($($arg)*);
+    };
+}
 
-// #[derive(Format, PartialEq, Clone, Copy)]
 #[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 enum GameState {
     Menu,
     Displaying,
@@ -30,195 +69,545 @@ enum GameState {
     Score,
 }
 
+const MAX_SEQUENCE: usize = 128;
+// The smallest screen the layout is made for: the HUD's level, hearts and progress side by side,
+// and a row of blocks below the HUD
+pub const MIN_WIDTH: u32 = 64;
+pub const MIN_HEIGHT: u32 = 16;
+
 pub struct Game<'a, Device: AbstractDevice> {
     device: Device,
+    settings: Settings,
     text_style: MonoTextStyle<'a, BinaryColor>,
+    hud_style: MonoTextStyle<'a, BinaryColor>,
     rng: fastrand::Rng,
+    // The seed is asked for when the first game starts, so devices can use how long the player
+    // took as entropy
+    seeded: bool,
+    // Shown on the menu until a button is pressed
+    notice: Option<&'a str>,
+    // Set when the low battery notice was shown, until the battery is charged again
+    battery_warned: bool,
     cursor: Point,
+    // Vertical distance between wrapped rows of blocks
+    row_height: u32,
     screen_size: Size,
+    // What is being drawn this frame, and what the device is currently showing
+    frame: Framebuffer,
+    shown: Framebuffer,
+    debounce_patterns: [u8; 2],
+    inputs: Inputs,
+    button1_fell: bool,
+    button2_fell: bool,
+    sequence: [bool; MAX_SEQUENCE],
+    sequence_length: usize,
+    game_state: GameState,
+    last_game_state: GameState,
+    // How far into the current state's pauses the game is, 0 at the start of a frame
+    phase: u32,
+    // How many symbols of the shown sequence the RGB LED went through
+    presented: usize,
+    next_guess_index: usize,
+    highest_cleared: usize,
+    level: usize,
+    lives: u32,
+    first: bool,
 }
 
 impl<'a, Device: AbstractDevice> Game<'a, Device> {
-    pub fn new(mut device: Device) -> Result<Self, Device::Error> {
-        let rng = fastrand::Rng::with_seed(device.get_rng_seed());
+    pub fn new(device: Device) -> Result<Self, Device::Error> {
+        Self::with_settings(device, Settings::default())
+    }
+
+    pub fn with_settings(mut device: Device, settings: Settings) -> Result<Self, Device::Error> {
         let text_style = MonoTextStyleBuilder::new()
             .font(&FONT_6X10)
             .text_color(BinaryColor::On)
             .build();
+        let hud_style = MonoTextStyleBuilder::new()
+            .font(&FONT_4X6)
+            .text_color(BinaryColor::On)
+            .build();
         let screen_size = device.display().bounding_box().size;
         Ok(Self {
             device,
+            settings,
             text_style,
-            rng,
+            hud_style,
+            rng: fastrand::Rng::with_seed(0),
+            seeded: false,
+            notice: None,
+            battery_warned: false,
             cursor: Point::zero(),
+            row_height: FONT_HEIGHT,
             screen_size,
+            frame: Framebuffer::new(screen_size)?,
+            shown: Framebuffer::new(screen_size)?,
+            debounce_patterns: [0x00; 2],
+            inputs: Inputs::default(),
+            button1_fell: false,
+            button2_fell: false,
+            sequence: [false; MAX_SEQUENCE],
+            sequence_length: 0,
+            game_state: GameState::Menu,
+            last_game_state: GameState::Score,
+            phase: 0,
+            presented: 0,
+            next_guess_index: 0,
+            highest_cleared: 0,
+            level: 0,
+            lives: 0,
+            first: true,
         })
     }
-    pub fn run_game(&mut self) -> Result<(), Device::Error> {
-        let mut debouncer_storage = [0x00u8; 2];
-        let mut debounce = Debouncer::new(&mut debouncer_storage);
-
-        const MAX_SEQUENCE: usize = 128;
-        let mut sequence_storage = [MaybeUninit::new(false); MAX_SEQUENCE];
-        let mut sequence = FixedSliceVec::new(&mut sequence_storage[..]);
-        sequence.clear();
-        let mut game_state = GameState::Menu;
-        let mut last_game_state = GameState::Score;
-        let mut next_guess_index = 0;
-        let mut highest_cleared = 0;
-        let mut first = true;
+    pub fn into_device(self) -> Device {
+        self.device
+    }
+
+    pub fn device(&mut self) -> &mut Device {
+        &mut self.device
+    }
+
+    pub fn set_notice(&mut self, notice: &'a str) {
+        self.notice = Some(notice);
+    }
 
+    // For devices that stop after an error. It fails too if the display is what went wrong.
+    pub fn show_error(&mut self, error: ErrorCode) -> Result<(), Device::Error> {
+        let mut buffer = [0x00u8; 64];
+        let string = format_no_std::show(
+            &mut buffer,
+            format_args!("E{}: {}\n{}\nRestarting...", error.code(), error.name(), error.hint()),
+        )?;
+        self.show_message(string)
+    }
+
+    pub fn run_game(&mut self) -> Result<(), Device::Error> {
         loop {
+            let ms = self.tick()?;
+            if ms > 0 {
+                self.device.delay_ms(ms);
+            }
+        }
+    }
+
+    // Runs the game until it has to wait, and returns for how many ms. Devices that can't block in
+    // delay_ms call this from their own event loop instead of run_game.
+    pub fn tick(&mut self) -> Result<u32, Device::Error> {
+        if self.phase == 0 {
             let inputs = self.device.get_inputs()?;
-            let button1_down = inputs.button1_down;
-            let button2_down = inputs.button2_down;
-            let button1_fell = debounce.update(0, button1_down) == DebounceResult::Pressed;
-            let button2_fell = debounce.update(1, button2_down) == DebounceResult::Pressed;
+            if inputs != self.inputs {
+                log!(trace, "Inputs: {}", inputs);
+            }
+            self.inputs = inputs;
+            let mut debounce = Debouncer::new(&mut self.debounce_patterns);
+            let button1 = debounce.update(0, self.inputs.button1_down);
+            let button2 = debounce.update(1, self.inputs.button2_down);
+            if button1 != DebounceResult::NoChange || button2 != DebounceResult::NoChange {
+                log!(debug, "Buttons: {} {}", button1, button2);
+            }
+            self.button1_fell = button1 == DebounceResult::Pressed;
+            self.button2_fell = button2 == DebounceResult::Pressed;
 
-            self.device.display().clear(BinaryColor::Off)?;
+            self.clear();
             self.reset_cursor();
 
-            if last_game_state != game_state {
-                last_game_state = game_state;
-                // info!("New state: {}", game_state);
+            if self.last_game_state != self.game_state {
+                self.last_game_state = self.game_state;
+                log!(info, "New state: {}", self.game_state);
             }
-            match game_state {
-                GameState::Menu => {
+        }
+        let button1_down = self.inputs.button1_down;
+        let button2_down = self.inputs.button2_down;
+        let button1_fell = self.button1_fell;
+        let button2_fell = self.button2_fell;
+        match self.game_state {
+            GameState::Menu => {
+                if let Some(notice) = self.notice {
+                    self.draw_string(notice)?;
+                    // Only dismisses the notice, so a press meant for it doesn't start trying
+                    // buttons
+                    if button1_fell || button2_fell {
+                        self.notice = None;
+                    }
+                } else {
+                    if let Some(settings) = self.device.take_settings() {
+                        self.settings = settings;
+                    }
                     self.draw_string(
                         "Sequence memory! Try\nbuttons. Push both\nbuttons to start.",
                     )?;
+                    if let Some(level) = self.device.battery_level() {
+                        let x = self.screen_size.width.saturating_sub(BATTERY_WIDTH + 1);
+                        self.draw_battery(x as i32, level);
+                        if level > LOW_BATTERY_PERCENT {
+                            self.battery_warned = false;
+                        } else if !self.battery_warned {
+                            self.battery_warned = true;
+                            self.notice = Some(LOW_BATTERY_NOTICE);
+                        }
+                    }
                     if button1_down && button2_down {
-                        game_state = GameState::Displaying;
-                        next_guess_index = 0;
-                        highest_cleared = 0;
-                        self.set_starting_sequence(&mut sequence);
-                        first = true;
+                        if !self.seeded {
+                            self.rng = fastrand::Rng::with_seed(self.device.get_rng_seed());
+                            self.seeded = true;
+                        }
+                        let seed = self.rng.get_seed();
+                        self.game_state = GameState::Displaying;
+                        self.next_guess_index = 0;
+                        self.highest_cleared = 0;
+                        self.level = 1;
+                        self.lives = self.settings.mode.starting_lives();
+                        self.set_starting_sequence();
+                        self.first = true;
+                        log!(info, "Game started with {} lives", self.lives);
+                        self.device.on_event(GameEvent::GameStarted {
+                            settings: self.settings,
+                            seed,
+                        });
                     } else {
                         if button1_fell {
-                            sequence.push(false);
+                            self.push_symbol(false);
                         } else if button2_fell {
-                            sequence.push(true);
+                            self.push_symbol(true);
                         }
-                        self.draw_sequence(&sequence, sequence.len(), None)?;
+                        self.draw_sequence(self.sequence_length, None)?;
                     }
                 }
-                GameState::Displaying => {
-                    if first {
-                        self.display_temporary_message("Remember!", 1000)?;
+            }
+            GameState::Displaying => {
+                if self.phase == 0 {
+                    self.phase = 1;
+                    if self.first {
+                        self.show_message("Remember!")?;
+                        return Ok(1000);
                     }
-                    self.draw_string(": ")?;
-                    self.draw_sequence(&sequence, sequence.len(), None)?;
-                    self.device.set_led(true);
-                    self.device.flush_display()?;
-                    self.device.set_led(false);
-                    if sequence.len() > 6 {
-                        self.device
-                            .delay_ms(2000 + 200 * (sequence.len() as u32 - 6));
+                }
+                if self.phase == 1 {
+                    self.clear();
+                    self.reset_cursor();
+                    self.draw_hud()?;
+                    self.draw_sequence(self.sequence_length, None)?;
+                    self.flush()?;
+                    log!(
+                        info,
+                        "Level {}: {} symbols",
+                        self.level,
+                        self.sequence_length
+                    );
+                    self.device.on_event(GameEvent::SequenceShown {
+                        level: self.level,
+                        sequence: &self.sequence[..self.sequence_length],
+                    });
+                    self.phase = 2;
+                    self.presented = 0;
+                }
+                if self.phase == 2 && self.presented < self.sequence_length {
+                    // The RGB LED steps through the symbols' colours in the time the sequence is
+                    // shown for
+                    let total_ms = self.settings.difficulty.display_ms(self.sequence_length);
+                    let length = self.sequence_length as u32;
+                    let index = self.presented as u32;
+                    let ms = total_ms * (index + 1) / length - total_ms * index / length;
+                    let value = self.sequence[self.presented];
+                    self.device.set_rgb_led(SYMBOL_COLORS[value as usize]);
+                    self.presented += 1;
+                    return Ok(ms);
+                }
+                if self.phase == 2 && self.first {
+                    self.phase = 3;
+                    self.show_message("Repeat!")?;
+                    return Ok(1000);
+                }
+                if self.phase == 3 {
+                    self.clear();
+                    self.reset_cursor();
+                }
+                self.game_state = GameState::Inputting;
+                self.device.set_rgb_led(Rgb888::BLACK);
+                self.device.on_event(GameEvent::SequenceHidden);
+            }
+            GameState::Inputting => {
+                let expected = self.sequence[self.next_guess_index];
+                if button1_fell || button2_fell {
+                    // Button 1 wins when both are pressed at once, like below
+                    let value = !button1_fell;
+                    let correct = value == expected;
+                    log!(
+                        info,
+                        "Input {}: button {}, correct: {}",
+                        self.next_guess_index,
+                        value as u8 + 1,
+                        correct
+                    );
+                    let color = if correct {
+                        CORRECT_COLOR
                     } else {
-                        self.device.delay_ms(2000);
-                    }
-                    game_state = GameState::Inputting;
-                    if first {
-                        self.display_temporary_message("Repeat!", 1000)?;
-                    }
+                        INCORRECT_COLOR
+                    };
+                    self.device.set_rgb_led(color);
+                    self.device.on_event(GameEvent::Input {
+                        index: self.next_guess_index,
+                        value,
+                        correct,
+                    });
                 }
-                GameState::Inputting => {
-                    self.draw_string(": ")?;
-                    if button1_fell {
-                        if sequence[next_guess_index] == false {
-                            next_guess_index += 1;
-                        } else {
-                            game_state = GameState::Failure;
-                        }
-                    } else if button2_fell {
-                        if sequence[next_guess_index] == true {
-                            next_guess_index += 1;
-                        } else {
-                            game_state = GameState::Failure;
-                        }
+                if button1_fell {
+                    if expected == false {
+                        self.next_guess_index += 1;
+                    } else {
+                        self.game_state = GameState::Failure;
                     }
-                    self.draw_sequence(&sequence, next_guess_index, None)?;
-                    if next_guess_index == sequence.len() {
-                        game_state = GameState::Next;
+                } else if button2_fell {
+                    if expected == true {
+                        self.next_guess_index += 1;
+                    } else {
+                        self.game_state = GameState::Failure;
                     }
                 }
-                GameState::Next => {
-                    self.display_temporary_message("Good! Next:", 400)?;
-                    next_guess_index = 0;
-                    highest_cleared = sequence.len();
-                    game_state = GameState::Displaying;
-                    first = false;
-                    self.generate_sequence(sequence.len() + 1, &mut sequence);
+                self.draw_hud()?;
+                self.draw_sequence(self.next_guess_index, None)?;
+                if self.next_guess_index == self.sequence_length {
+                    self.game_state = GameState::Next;
                 }
-                GameState::Failure => {
-                    self.display_temporary_message("No!", 200)?;
-
-                    // Blink for a bit to show where the user messed up
-                    for i in 0..6 {
-                        self.device.display().clear(BinaryColor::Off)?;
-                        self.reset_cursor();
-                        self.draw_string(": ")?;
-
-                        let hide_index = if i % 2 == 0 {
-                            Some(next_guess_index)
-                        } else {
-                            None
-                        };
-
-                        self.draw_sequence(&sequence, sequence.len(), hide_index)?;
-                        self.device.set_led(true);
-                        self.device.flush_display()?;
-                        self.device.set_led(false);
-                        self.device.delay_ms(500);
+            }
+            GameState::Next => {
+                let step = self.phase as usize;
+                if step < LEVEL_UP_COLORS.len() {
+                    if step == 0 {
+                        self.show_message("Good! Next:")?;
                     }
-                    game_state = GameState::Score;
+                    self.device.set_rgb_led(LEVEL_UP_COLORS[step]);
+                    self.phase += 1;
+                    return Ok(LEVEL_UP_MS / LEVEL_UP_COLORS.len() as u32);
                 }
-                GameState::Score => {
-                    self.draw_string("You cleared ")?;
-                    let score =
-                        highest_cleared as f32 + next_guess_index as f32 / sequence.len() as f32;
-                    self.draw_float_string(score)?;
-                    self.cursor = Point::new(0, 10);
-                    self.draw_string("sequences!")?;
-                    if button1_fell || button2_fell {
-                        game_state = GameState::Menu;
-                        sequence.clear();
-                    }
+                self.clear();
+                self.reset_cursor();
+                self.next_guess_index = 0;
+                self.highest_cleared = self.sequence_length;
+                self.level += 1;
+                self.game_state = GameState::Displaying;
+                self.first = false;
+                self.generate_sequence(self.sequence_length + 1);
+            }
+            GameState::Failure => {
+                // Blink for a bit to show where the user messed up
+                const BLINKS: u32 = 6;
+                if self.phase == 0 {
+                    self.phase = 1;
+                    self.show_message("No!")?;
+                    return Ok(200);
+                }
+                if self.phase <= BLINKS {
+                    self.clear();
+                    self.reset_cursor();
+                    self.draw_hud()?;
+
+                    let hide_index = if self.phase % 2 == 1 {
+                        Some(self.next_guess_index)
+                    } else {
+                        None
+                    };
+
+                    self.draw_sequence(self.sequence_length, hide_index)?;
+                    self.flush()?;
+                    self.phase += 1;
+                    return Ok(500);
+                }
+                self.lives -= 1;
+                if self.lives == 0 {
+                    self.game_state = GameState::Score;
+                    log!(info, "Game over, score {}", self.score());
+                    self.device.set_rgb_led(Rgb888::BLACK);
+                    self.device.on_event(GameEvent::GameOver {
+                        score: self.score(),
+                    });
+                } else {
+                    // Try the same sequence again
+                    self.next_guess_index = 0;
+                    self.game_state = GameState::Displaying;
+                }
+            }
+            GameState::Score => {
+                self.draw_string("You cleared ")?;
+                self.draw_float_string(self.score())?;
+                self.cursor = Point::new(0, 10);
+                self.draw_string("sequences!")?;
+                if button1_fell || button2_fell {
+                    self.game_state = GameState::Menu;
+                    self.sequence_length = 0;
                 }
             }
-            // self.device.delay_ms(10);
-            self.device.set_led(true);
-            self.device.flush_display()?;
-            self.device.set_led(false);
         }
+        self.phase = 0;
+        if !self.flush()? {
+            return Ok(IDLE_POLL_MS);
+        }
+        Ok(0)
     }
 
-    fn set_starting_sequence(&self, sequence: &mut FixedSliceVec<bool>) {
-        sequence.clear();
-        sequence.push(false);
-        sequence.push(false);
-        sequence.push(true);
+    // Sequences cleared, plus the part of the one the game ended on
+    fn score(&self) -> f32 {
+        self.highest_cleared as f32 + self.next_guess_index as f32 / self.sequence_length as f32
     }
-    fn generate_sequence(&mut self, length: usize, sequence: &mut FixedSliceVec<bool>) {
-        sequence.clear();
+
+    fn push_symbol(&mut self, value: bool) {
+        if self.sequence_length < MAX_SEQUENCE {
+            self.sequence[self.sequence_length] = value;
+            self.sequence_length += 1;
+        }
+    }
+
+    fn set_starting_sequence(&mut self) {
+        // Games that start at the usual length open with the same warm-up sequence
+        const WARM_UP: [bool; 3] = [false, false, true];
+        let length = self.settings.difficulty.starting_length();
+        if length == WARM_UP.len() {
+            self.sequence_length = 0;
+            for value in WARM_UP {
+                self.push_symbol(value);
+            }
+        } else {
+            self.generate_sequence(length);
+        }
+    }
+    fn generate_sequence(&mut self, length: usize) {
+        self.sequence_length = 0;
         for _ in 0..length {
-            sequence.push(self.rng.bool());
+            let value = self.rng.bool();
+            self.push_symbol(value);
         }
     }
 
+    fn clear(&mut self) {
+        let Ok(()) = self.frame.clear(BinaryColor::Off);
+    }
+
+    // Hands only the pixels that changed since the last flush to the device, along with the area
+    // they are in, and skips the flush entirely when nothing did. Returns whether anything was
+    // flushed.
+    fn flush(&mut self) -> Result<bool, Device::Error> {
+        let Some(area) = self.frame.changed_area(&self.shown) else {
+            return Ok(false);
+        };
+        let pixels = area
+            .points()
+            .map(|point| Pixel(point, BinaryColor::from(self.frame.get_pixel(point))));
+        self.device.display().draw_iter(pixels)?;
+        self.shown.clone_from(&self.frame);
+        self.device.set_led(true);
+        self.device.flush_display(area)?;
+        self.device.set_led(false);
+        Ok(true)
+    }
+
     fn reset_cursor(&mut self) {
         self.cursor = Point::zero();
+        self.row_height = FONT_HEIGHT;
+    }
+
+    // Draws the level, lives and progress strip, with the battery while it is low, and leaves the
+    // cursor at the top of the sequence area below it with rows sized so the whole sequence fits
+    fn draw_hud(&mut self) -> Result<(), Device::Error> {
+        let (level, lives) = (self.level, self.lives);
+        let (progress, sequence_length) = (self.next_guess_index, self.sequence_length);
+        let mut buffer = [0x00u8; 12];
+        let string = format_no_std::show(&mut buffer, format_args!("LV {}", level))?;
+        let Ok(_) = Text::with_baseline(string, Point::zero(), self.hud_style, Baseline::Top)
+            .draw(&mut self.frame);
+
+        let string =
+            format_no_std::show(&mut buffer, format_args!("{}/{}", progress, sequence_length))?;
+        let right_aligned = TextStyleBuilder::new()
+            .baseline(Baseline::Top)
+            .alignment(Alignment::Right)
+            .build();
+        let Ok(_) = Text::with_text_style(
+            string,
+            Point::new(self.screen_size.width as i32 - 1, 0),
+            self.hud_style,
+            right_aligned,
+        )
+        .draw(&mut self.frame);
+        // Playing is when the battery runs out, so a low one stays in sight left of the progress
+        if let Some(level) = self.device.battery_level() {
+            if level <= LOW_BATTERY_PERCENT {
+                let width = string.len() as u32 * HUD_FONT_WIDTH + BATTERY_WIDTH + 2;
+                let x = self.screen_size.width.saturating_sub(width);
+                self.draw_battery(x as i32, level);
+            }
+        }
+
+        if self.settings.mode == GameMode::Lives {
+            let heart = ImageRaw::<BinaryColor>::new(&HEART, HEART_WIDTH);
+            let hearts_width = lives * (HEART_WIDTH + 1);
+            // Centred, or from the left edge and clipped when the screen is narrower than them
+            let x = self.screen_size.width.saturating_sub(hearts_width) / 2;
+            let mut position = Point::new(x as i32, 1);
+            for _ in 0..lives {
+                let Ok(()) = Image::new(&heart, position).draw(&mut self.frame);
+                position.x += HEART_WIDTH as i32 + 1;
+            }
+        }
+
+        let separator_y = HUD_HEIGHT as i32 - 2;
+        let Ok(()) = Line::new(
+            Point::new(0, separator_y),
+            Point::new(self.screen_size.width as i32 - 1, separator_y),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(&mut self.frame);
+
+        self.cursor = Point::new(0, HUD_HEIGHT as i32);
+        let rows = self.count_block_rows(sequence_length);
+        let available = self.screen_size.height.saturating_sub(HUD_HEIGHT);
+        self.row_height = (available / rows).clamp(MIN_BLOCK_ROW_HEIGHT, FONT_HEIGHT);
+        Ok(())
+    }
+
+    // An outline filled in proportion to the level, rounded up so a nearly empty battery still shows
+    fn draw_battery(&mut self, x: i32, level: u8) {
+        let outline = Rectangle::new(Point::new(x, 0), Size::new(BATTERY_WIDTH, BATTERY_HEIGHT));
+        let Ok(()) = outline
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut self.frame);
+        let nub = Rectangle::new(Point::new(x + BATTERY_WIDTH as i32, 1), Size::new(1, 3));
+        let inside = BATTERY_WIDTH - 2;
+        let filled = (level.min(100) as u32 * inside).div_ceil(100);
+        let fill = Rectangle::new(Point::new(x + 1, 1), Size::new(filled, BATTERY_HEIGHT - 2));
+        for part in [nub, fill] {
+            let Ok(()) = part
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(&mut self.frame);
+        }
+    }
+
+    // Number of rows draw_sequence wraps `length` blocks onto, starting from the cursor
+    fn count_block_rows(&self, length: usize) -> u32 {
+        let mut x = self.cursor.x;
+        let mut rows = 1;
+        for i in 0..length {
+            if i % 3 == 0 {
+                x += BLOCK_GROUPING_EXTRA_SPACING as i32;
+            }
+            if x as u32 > self.screen_size.width.saturating_sub(FONT_WIDTH) {
+                x = 0;
+                rows += 1;
+            }
+            x += FONT_WIDTH as i32 + BLOCK_SPACE as i32;
+        }
+        rows
     }
     fn draw_string(&mut self, string: &str) -> Result<(), Device::Error> {
-        let next_point = Text::with_baseline(string, self.cursor, self.text_style, Baseline::Top)
-            .draw(self.device.display())?;
+        let Ok(next_point) =
+            Text::with_baseline(string, self.cursor, self.text_style, Baseline::Top)
+                .draw(&mut self.frame);
         self.cursor = next_point;
         Ok(())
     }
 
     fn draw_sequence(
         &mut self,
-        sequence: &FixedSliceVec<bool>,
         subset_length: usize,
         hide_index: Option<usize>,
     ) -> Result<(), Device::Error> {
@@ -229,7 +618,7 @@ impl<'a, Device: AbstractDevice> Game<'a, Device> {
         };
 
         for i in 0..subset_length {
-            let value = sequence[i];
+            let value = self.sequence[i];
             if i % 3 == 0 {
                 // Create a grouping that's easier to parse when facing long sequences
                 self.cursor.x += BLOCK_GROUPING_EXTRA_SPACING as i32;
@@ -246,52 +635,45 @@ impl<'a, Device: AbstractDevice> Game<'a, Device> {
         Ok(())
     }
 
-    fn _draw_string_wrapping(&mut self, string: &str) -> Result<(), Device::Error> {
-        if self.cursor.x as u32 > self.screen_size.width - (FONT_WIDTH * string.len() as u32) {
-            self.cursor.x = 0;
-            self.cursor.y += FONT_HEIGHT as i32;
-        }
-        self.draw_string(string)?;
-        Ok(())
-    }
-
     fn draw_block_wrapping(
         &mut self,
         value: bool,
         color: BinaryColor,
     ) -> Result<(), Device::Error> {
-        if self.cursor.x as u32 > self.screen_size.width - FONT_WIDTH {
+        if self.cursor.x as u32 > self.screen_size.width.saturating_sub(FONT_WIDTH) {
             self.cursor.x = 0;
-            self.cursor.y += FONT_HEIGHT as i32;
+            self.cursor.y += self.row_height as i32;
         }
+        let block_height = self.row_height - 1;
+        let line_height = if block_height > 2 * BLOCK_LINE_HEIGHT {
+            BLOCK_LINE_HEIGHT
+        } else {
+            1
+        };
         let block = if value {
-            Rectangle::new(self.cursor, Size::new(FONT_WIDTH, FONT_HEIGHT - 1))
+            Rectangle::new(self.cursor, Size::new(FONT_WIDTH, block_height))
         } else {
             Rectangle::new(
                 Point::new(
                     self.cursor.x,
-                    self.cursor.y + FONT_HEIGHT as i32 - BLOCK_LINE_HEIGHT as i32 - 1,
+                    self.cursor.y + block_height as i32 - line_height as i32,
                 ),
-                Size::new(FONT_WIDTH, BLOCK_LINE_HEIGHT),
+                Size::new(FONT_WIDTH, line_height),
             )
         };
-        block
+        let Ok(()) = block
             .into_styled(PrimitiveStyle::with_fill(color))
-            .draw(self.device.display())?;
+            .draw(&mut self.frame);
         self.cursor.x += FONT_WIDTH as i32 + BLOCK_SPACE as i32;
         Ok(())
     }
 
-    fn display_temporary_message(&mut self, string: &str, ms: u32) -> Result<(), Device::Error> {
-        self.device.display().clear(BinaryColor::Off)?;
+    // Shows just this message, for as long as the caller waits before drawing the next frame
+    fn show_message(&mut self, string: &str) -> Result<(), Device::Error> {
+        self.clear();
         self.reset_cursor();
         self.draw_string(string)?;
-        self.device.set_led(true);
-        self.device.flush_display()?;
-        self.device.set_led(false);
-        self.device.delay_ms(ms);
-        self.device.display().clear(BinaryColor::Off)?;
-        self.reset_cursor();
+        self.flush()?;
         Ok(())
     }
 
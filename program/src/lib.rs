@@ -1,4 +1,10 @@
 #![no_std]
 pub mod game;
 pub mod debouncing;
-pub mod abstract_device;
\ No newline at end of file
+pub mod abstract_device;
+pub mod framebuffer;
+pub mod settings;
+pub mod stats;
+pub mod console;
+pub mod seed;
+pub mod error_code;
\ No newline at end of file
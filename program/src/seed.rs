@@ -0,0 +1,112 @@
+// Added between samples so that zeros still move the state along
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// The SplitMix64 finaliser: every input bit affects about half of the output bits
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Hashes any number of entropy samples into a 64 bit seed. Samples can be weak on their own, like
+// an ADC reading that only changes in its low bits, since each one changes the whole seed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SeedMixer {
+    state: u64,
+}
+
+impl Default for SeedMixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SeedMixer {
+    pub fn new() -> Self {
+        SeedMixer { state: 0 }
+    }
+
+    pub fn add(&mut self, sample: u64) {
+        self.state = mix(self.state ^ sample).wrapping_add(GAMMA);
+    }
+
+    pub fn finish(&self) -> u64 {
+        mix(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use crate::seed::SeedMixer;
+
+    // Seeds from every reading of a 12-bit ADC, the only entropy the Pico used to have
+    fn adc_seeds() -> impl Iterator<Item = u64> {
+        (0..4096).map(|reading| {
+            let mut mixer = SeedMixer::new();
+            mixer.add(reading);
+            mixer.finish()
+        })
+    }
+
+    #[test]
+    fn test_avalanche() {
+        let mut flipped_bits = 0;
+        let mut count = 0;
+        for reading in 0..4096u64 {
+            for bit in 0..12 {
+                let seed = |reading| {
+                    let mut mixer = SeedMixer::new();
+                    mixer.add(0x1234);
+                    mixer.add(reading);
+                    mixer.finish()
+                };
+                flipped_bits += (seed(reading) ^ seed(reading ^ 1 << bit)).count_ones();
+                count += 1;
+            }
+        }
+        let mean = flipped_bits as f64 / count as f64;
+        assert!((31.0..33.0).contains(&mean), "{} bits flipped on average", mean);
+    }
+
+    // The game draws each symbol with Rng::bool, so look at sequences made the same way
+    #[test]
+    fn test_sequences() {
+        const LENGTH: usize = 32;
+        let sequences: std::vec::Vec<u32> = adc_seeds()
+            .map(|seed| {
+                let mut rng = fastrand::Rng::with_seed(seed);
+                (0..LENGTH).fold(0, |bits, index| bits | (rng.bool() as u32) << index)
+            })
+            .collect();
+
+        // Each position is button 2 about half of the time. With 4096 sequences the standard
+        // deviation is under 0.008, so this allows for more than 6 of them.
+        for index in 0..LENGTH {
+            let ones = sequences.iter().filter(|&&bits| bits >> index & 1 == 1).count();
+            let fraction = ones as f64 / sequences.len() as f64;
+            assert!((0.45..0.55).contains(&fraction), "position {}: {}", index, fraction);
+        }
+
+        // Pairs of neighbouring symbols are all about as common, so symbols don't follow each other
+        let mut pairs = [0; 4];
+        for bits in &sequences {
+            for index in 0..LENGTH - 1 {
+                pairs[(bits >> index & 0b11) as usize] += 1;
+            }
+        }
+        let expected = (sequences.len() * (LENGTH - 1)) as f64 / 4.0;
+        let chi_squared: f64 = pairs
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // The 99.9th percentile of the chi-squared distribution with 3 degrees of freedom
+        assert!(chi_squared < 16.27, "chi-squared {} for {:?}", chi_squared, pairs);
+
+        // And no two readings give the same game
+        let mut unique = sequences.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), sequences.len());
+    }
+}
@@ -0,0 +1,117 @@
+use core::fmt;
+use core::str::FromStr;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameMode {
+    // The first mistake ends the game
+    Classic,
+    // A mistake costs a life and the same sequence is shown again
+    Lives,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: GameMode::Classic,
+            difficulty: Difficulty::Normal,
+        }
+    }
+}
+
+pub const STARTING_LIVES: u32 = 3;
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Classic, GameMode::Lives];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Lives => "lives",
+        }
+    }
+
+    pub fn starting_lives(self) -> u32 {
+        match self {
+            GameMode::Classic => 1,
+            GameMode::Lives => STARTING_LIVES,
+        }
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Length of the first sequence of a game
+    pub fn starting_length(self) -> usize {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 3,
+            Difficulty::Hard => 5,
+        }
+    }
+
+    // How long a sequence stays on screen before it has to be repeated
+    pub fn display_ms(self, length: usize) -> u32 {
+        // Sequences up to this long get the base time, longer ones get extra time per symbol
+        const BASE_LENGTH: usize = 6;
+        let (base_ms, per_symbol_ms) = match self {
+            Difficulty::Easy => (3000, 400),
+            Difficulty::Normal => (2000, 200),
+            Difficulty::Hard => (1200, 100),
+        };
+        base_ms + per_symbol_ms * length.saturating_sub(BASE_LENGTH) as u32
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseSettingError;
+
+impl fmt::Display for ParseSettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown value")
+    }
+}
+
+impl core::error::Error for ParseSettingError {}
+
+impl FromStr for GameMode {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameMode::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseSettingError)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseSettingError)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::abstract_device::GameEvent;
+use crate::settings::{Difficulty, GameMode, Settings};
+
+// Games and scores for one mode and difficulty
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ModeStats {
+    pub games: u32,
+    pub best_score: f32,
+    pub total_score: f32,
+}
+
+impl ModeStats {
+    pub fn mean_score(&self) -> f32 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.total_score / self.games as f32
+        }
+    }
+}
+
+// Counted from game events, for devices that have no file to keep a history in
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Stats {
+    modes: [[ModeStats; Difficulty::ALL.len()]; GameMode::ALL.len()],
+    // Settings of the game being played
+    current: Option<Settings>,
+    pub inputs: u32,
+    pub correct_inputs: u32,
+}
+
+impl Stats {
+    pub fn record(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::GameStarted { settings, .. } => self.current = Some(settings),
+            GameEvent::Input { correct, .. } => {
+                self.inputs += 1;
+                if correct {
+                    self.correct_inputs += 1;
+                }
+            }
+            GameEvent::GameOver { score } => {
+                if let Some(settings) = self.current.take() {
+                    let stats = &mut self.modes[settings.mode as usize][settings.difficulty as usize];
+                    stats.games += 1;
+                    stats.total_score += score;
+                    stats.best_score = stats.best_score.max(score);
+                }
+            }
+            GameEvent::SequenceShown { .. } | GameEvent::SequenceHidden => {}
+        }
+    }
+
+    pub fn get(&self, settings: Settings) -> ModeStats {
+        self.modes[settings.mode as usize][settings.difficulty as usize]
+    }
+
+    pub fn games(&self) -> u32 {
+        self.played().map(|(_, stats)| stats.games).sum()
+    }
+
+    // Each mode and difficulty with at least one finished game
+    pub fn played(&self) -> impl Iterator<Item = (Settings, ModeStats)> + '_ {
+        GameMode::ALL
+            .into_iter()
+            .flat_map(|mode| {
+                Difficulty::ALL
+                    .into_iter()
+                    .map(move |difficulty| Settings { mode, difficulty })
+            })
+            .map(|settings| (settings, self.get(settings)))
+            .filter(|(_, stats)| stats.games > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::abstract_device::GameEvent;
+    use crate::settings::{Difficulty, GameMode, Settings};
+    use crate::stats::{ModeStats, Stats};
+
+    #[test]
+    fn test_record() {
+        let lives = Settings {
+            mode: GameMode::Lives,
+            difficulty: Difficulty::Hard,
+        };
+        let mut stats = Stats::default();
+        for (settings, score) in [(Settings::default(), 3.0), (lives, 7.5), (Settings::default(), 5.0)] {
+            stats.record(GameEvent::GameStarted { settings, seed: 0 });
+            stats.record(GameEvent::Input { index: 0, value: false, correct: true });
+            stats.record(GameEvent::Input { index: 1, value: true, correct: false });
+            stats.record(GameEvent::GameOver { score });
+        }
+        // A game over without a start isn't counted
+        stats.record(GameEvent::GameOver { score: 100.0 });
+
+        assert_eq!(stats.games(), 3);
+        assert_eq!((stats.inputs, stats.correct_inputs), (6, 3));
+        assert_eq!(
+            stats.get(Settings::default()),
+            ModeStats {
+                games: 2,
+                best_score: 5.0,
+                total_score: 8.0,
+            }
+        );
+        assert_eq!(stats.get(Settings::default()).mean_score(), 4.0);
+        let played: [Settings; 2] = core::array::from_fn(|index| stats.played().nth(index).unwrap().0);
+        assert_eq!(played, [Settings::default(), lives]);
+        assert_eq!(stats.played().count(), 2);
+    }
+}
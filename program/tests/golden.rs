@@ -0,0 +1,500 @@
+// Renders every game screen into an in-memory framebuffer and compares the flushed frames against
+// the text-art images in tests/golden. After an intended rendering change, re-bless them with
+//
+//     BLESS=1 cargo test -p program --test golden
+//
+// and review the diff of tests/golden before committing it.
+
+use core::convert::Infallible;
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888, RgbColor};
+use embedded_graphics::prelude::DrawTarget;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::error_code::ErrorCode;
+use program::framebuffer::{Framebuffer, TooLarge};
+use program::game::Game;
+use program::settings::{Difficulty, GameMode, Settings};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+const SEED: u64 = 0x5eed;
+
+#[derive(Debug)]
+enum Error {
+    // The script ran out of inputs, which is how every scenario ends
+    ScriptEnded,
+    Format,
+    TooLarge(#[allow(dead_code)] Size),
+}
+
+impl From<Infallible> for Error {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+impl From<core::fmt::Error> for Error {
+    fn from(_value: core::fmt::Error) -> Self {
+        Self::Format
+    }
+}
+
+impl From<TooLarge> for Error {
+    fn from(TooLarge(size): TooLarge) -> Self {
+        Self::TooLarge(size)
+    }
+}
+
+// One step of a script: hold these buttons for this many frames
+struct Step {
+    frames: u32,
+    button1_down: bool,
+    button2_down: bool,
+}
+
+const fn hold(frames: u32, button1_down: bool, button2_down: bool) -> Step {
+    Step {
+        frames,
+        button1_down,
+        button2_down,
+    }
+}
+
+// Long enough for the debouncer to register a press and then a release
+const fn press(button1_down: bool, button2_down: bool) -> [Step; 2] {
+    [
+        hold(4, button1_down, button2_down),
+        hold(4, false, false),
+    ]
+}
+
+struct ScriptedDevice {
+    display: Framebuffer,
+    // The display at the last flush, to check the game's areas cover what it drew since
+    flushed: Framebuffer,
+    script: Vec<Step>,
+    step: usize,
+    frames_left: u32,
+    time_ms: u64,
+    output: String,
+    flushes: u32,
+    events: Vec<String>,
+    battery: Option<u8>,
+    rgb_led: Vec<String>,
+}
+
+impl ScriptedDevice {
+    fn new(size: Size, script: Vec<Step>) -> Self {
+        let frames_left = script.first().map_or(0, |step| step.frames);
+        Self {
+            display: Framebuffer::new(size).unwrap(),
+            flushed: Framebuffer::new(size).unwrap(),
+            script,
+            step: 0,
+            frames_left,
+            time_ms: 0,
+            output: String::new(),
+            flushes: 0,
+            events: Vec::new(),
+            battery: None,
+            rgb_led: Vec::new(),
+        }
+    }
+}
+
+impl AbstractDevice for ScriptedDevice {
+    type Display = Framebuffer;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Self::Error> {
+        while self.frames_left == 0 {
+            self.step += 1;
+            match self.script.get(self.step) {
+                Some(step) => self.frames_left = step.frames,
+                None => return Err(Error::ScriptEnded),
+            }
+        }
+        self.frames_left -= 1;
+        let step = &self.script[self.step];
+        Ok(Inputs {
+            button1_down: step.button1_down,
+            button2_down: step.button2_down,
+        })
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.time_ms += ms as u64;
+    }
+
+    fn get_rng_seed(&mut self) -> u64 {
+        SEED
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display
+    }
+
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+        let changed = self.display.changed_area(&self.flushed).unwrap();
+        assert_eq!(area.intersection(&changed), changed);
+        self.flushed.clone_from(&self.display);
+        self.flushes += 1;
+        writeln!(self.output, "frame {} at {} ms", self.flushes, self.time_ms)?;
+        let size = self.display.size();
+        for y in 0..size.height as i32 {
+            for x in 0..size.width as i32 {
+                let pixel = self.display.get_pixel(Point::new(x, y));
+                self.output.push(if pixel { '#' } else { '.' });
+            }
+            self.output.push('\n');
+        }
+        self.output.push('\n');
+        Ok(())
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        let event = match event {
+            GameEvent::GameStarted { settings, .. } => {
+                format!(
+                    "started {} {}",
+                    settings.mode.name(),
+                    settings.difficulty.name()
+                )
+            }
+            GameEvent::SequenceShown { level, sequence } => {
+                let symbols: String = sequence
+                    .iter()
+                    .map(|&value| if value { '2' } else { '1' })
+                    .collect();
+                format!("level {} shows {}", level, symbols)
+            }
+            GameEvent::SequenceHidden => "hidden".into(),
+            GameEvent::Input {
+                index,
+                value,
+                correct,
+            } => {
+                format!(
+                    "input {} = {} {}",
+                    index,
+                    value as u8 + 1,
+                    if correct { "ok" } else { "wrong" }
+                )
+            }
+            GameEvent::GameOver { score } => format!("game over {:.2}", score),
+        };
+        self.events.push(format!("{} ms: {}", self.time_ms, event));
+    }
+
+    fn battery_level(&mut self) -> Option<u8> {
+        self.battery
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        let name = match color {
+            Rgb888::BLACK => "off",
+            Rgb888::RED => "red",
+            Rgb888::GREEN => "green",
+            Rgb888::BLUE => "blue",
+            Rgb888::YELLOW => "yellow",
+            Rgb888::CYAN => "cyan",
+            Rgb888::MAGENTA => "magenta",
+            Rgb888::WHITE => "white",
+            _ => "other",
+        };
+        self.rgb_led.push(format!("{} ms: {}", self.time_ms, name));
+    }
+}
+
+fn run_scenario(name: &str, size: Size, settings: Settings, script: Vec<Step>) {
+    let device = ScriptedDevice::new(size, script);
+    run_game(name, Game::with_settings(device, settings).unwrap());
+}
+
+fn run_game(name: &str, mut game: Game<ScriptedDevice>) {
+    let result = game.run_game();
+    assert!(matches!(result, Err(Error::ScriptEnded)), "{:?}", result);
+    check_golden(name, &game.into_device().output);
+}
+
+fn check_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name));
+    if std::env::var_os("BLESS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("{}: {}, run with BLESS=1", path.display(), error));
+    if let Some((line, (expected, actual))) = expected
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+    {
+        panic!(
+            "{} differs at line {}:\nexpected {}\n  actual {}\nrun with BLESS=1 to update",
+            path.display(),
+            line + 1,
+            expected,
+            actual
+        );
+    }
+    assert_eq!(
+        expected.lines().count(),
+        actual.lines().count(),
+        "{} has a different number of frames, run with BLESS=1 to update",
+        path.display()
+    );
+}
+
+// Idle menu, then enough practice presses of each button to wrap onto a new row
+#[test]
+fn test_menu() {
+    let mut script = vec![hold(3, false, false)];
+    for _ in 0..6 {
+        script.extend(press(true, false));
+        script.extend(press(false, true));
+    }
+    run_scenario("menu", Size::new(128, 32), Settings::default(), script);
+}
+
+// A notice on the menu, which the first press only dismisses
+#[test]
+fn test_notice() {
+    let mut script = vec![hold(3, false, false)];
+    script.extend(press(true, false));
+    script.push(hold(3, false, false));
+    let device = ScriptedDevice::new(Size::new(128, 32), script);
+    let mut game = Game::new(device).unwrap();
+    game.set_notice("Restarted after\nI2C error\nRestarts: 2");
+    run_game("notice", game);
+}
+
+// The battery icon on the menu of a device running from a battery
+#[test]
+fn test_battery() {
+    let mut device = ScriptedDevice::new(Size::new(128, 32), vec![hold(3, false, false)]);
+    device.battery = Some(60);
+    run_game("battery", Game::new(device).unwrap());
+}
+
+// A low battery is warned about once, the menu doesn't bring the notice back after it's dismissed
+#[test]
+fn test_low_battery() {
+    let mut script = vec![hold(3, false, false)];
+    script.extend(press(true, false));
+    script.push(hold(3, false, false));
+    let mut device = ScriptedDevice::new(Size::new(128, 32), script);
+    device.battery = Some(5);
+    run_game("low_battery", Game::new(device).unwrap());
+}
+
+// A low battery stays in the HUD during play, after the notice is dismissed
+#[test]
+fn test_low_battery_game() {
+    let mut script = vec![hold(3, false, false)];
+    script.extend(press(true, false));
+    script.extend(full_game());
+    let mut device = ScriptedDevice::new(Size::new(128, 32), script);
+    device.battery = Some(5);
+    run_game("low_battery_game", Game::new(device).unwrap());
+}
+
+// A battery too small to show on the menu or in the HUD of a tiny screen
+#[test]
+fn test_low_battery_5x6() {
+    let mut script = vec![hold(3, false, false)];
+    script.extend(press(true, false));
+    script.extend(full_game());
+    let mut device = ScriptedDevice::new(Size::new(5, 6), script);
+    device.battery = Some(5);
+    run_game("low_battery_5x6", Game::new(device).unwrap());
+}
+
+// What a device shows before it stops after an error
+#[test]
+fn test_error_screen() {
+    let device = ScriptedDevice::new(Size::new(128, 32), Vec::new());
+    let mut game = Game::new(device).unwrap();
+    game.show_error(ErrorCode::Display).unwrap();
+    check_golden("error_screen", &game.into_device().output);
+}
+
+// Start a game, clear the fixed first sequence, fail the random second one, read the score and
+// return to the menu. This passes through every game state.
+fn full_game() -> Vec<Step> {
+    let mut script = vec![
+        hold(3, false, false),
+        hold(1, true, true),
+        hold(4, false, false),
+    ];
+    script.extend(press(true, false));
+    script.extend(press(true, false));
+    script.extend(press(false, true));
+    script.push(hold(2, false, false));
+    for _ in 0..4 {
+        script.extend(press(true, false));
+    }
+    script.push(hold(2, false, false));
+    script.extend(press(false, true));
+    script.push(hold(2, false, false));
+    script
+}
+
+#[test]
+fn test_game() {
+    run_scenario("game", Size::new(128, 32), Settings::default(), full_game());
+}
+
+#[test]
+fn test_game_128x64() {
+    run_scenario(
+        "game_128x64",
+        Size::new(128, 64),
+        Settings::default(),
+        full_game(),
+    );
+}
+
+// Screens smaller than the HUD and a block still play, with the layout clipped
+#[test]
+fn test_game_5x6() {
+    let settings = Settings::default();
+    run_scenario("game_5x6", Size::new(5, 6), settings, full_game());
+}
+
+// The same inputs lose a life instead of ending the game, then the retry runs out of inputs
+#[test]
+fn test_game_lives() {
+    let settings = Settings {
+        mode: GameMode::Lives,
+        difficulty: Difficulty::Hard,
+    };
+    run_scenario("game_lives", Size::new(128, 32), settings, full_game());
+}
+
+// Hearts wider than the screen
+#[test]
+fn test_game_lives_16x32() {
+    let settings = Settings {
+        mode: GameMode::Lives,
+        difficulty: Difficulty::Hard,
+    };
+    run_scenario("game_lives_16x32", Size::new(16, 32), settings, full_game());
+}
+
+// What a device keeping statistics hears about the same game
+#[test]
+fn test_game_events() {
+    let device = ScriptedDevice::new(Size::new(128, 32), full_game());
+    let mut game = Game::new(device).unwrap();
+    assert!(matches!(game.run_game(), Err(Error::ScriptEnded)));
+    let events = game.into_device().events;
+    assert_eq!(
+        events,
+        [
+            "20 ms: started classic normal",
+            "1030 ms: level 1 shows 112",
+            "4030 ms: hidden",
+            "4080 ms: input 0 = 1 ok",
+            "4140 ms: input 1 = 1 ok",
+            "4210 ms: input 2 = 2 ok",
+            "4610 ms: level 2 shows 2212",
+            "6610 ms: hidden",
+            "6680 ms: input 0 = 1 wrong",
+            "9890 ms: game over 3.00",
+        ]
+    );
+}
+
+// The RGB LED through the same game
+#[test]
+fn test_rgb_led() {
+    let device = ScriptedDevice::new(Size::new(128, 32), full_game());
+    let mut game = Game::new(device).unwrap();
+    assert!(matches!(game.run_game(), Err(Error::ScriptEnded)));
+    let rgb_led = game.into_device().rgb_led;
+    assert_eq!(
+        rgb_led,
+        [
+            "1030 ms: blue",
+            "1696 ms: blue",
+            "2363 ms: yellow",
+            "4030 ms: off",
+            "4080 ms: green",
+            "4140 ms: green",
+            "4210 ms: green",
+            "4210 ms: cyan",
+            "4310 ms: blue",
+            "4410 ms: magenta",
+            "4510 ms: white",
+            "4610 ms: yellow",
+            "5110 ms: yellow",
+            "5610 ms: blue",
+            "6110 ms: yellow",
+            "6610 ms: off",
+            "6680 ms: red",
+            "9890 ms: off",
+        ]
+    );
+}
+
+// A display larger than the game's frame, which only has to report its size
+struct Oversized;
+
+impl OriginDimensions for Oversized {
+    fn size(&self) -> Size {
+        Size::new(256, 64)
+    }
+}
+
+impl DrawTarget for Oversized {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        Ok(())
+    }
+}
+
+impl AbstractDevice for Oversized {
+    type Display = Oversized;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Self::Error> {
+        Ok(Inputs::default())
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    fn delay_ms(&mut self, _ms: u32) {}
+
+    fn get_rng_seed(&mut self) -> u64 {
+        SEED
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        self
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_too_large() {
+    assert!(matches!(
+        Game::new(Oversized),
+        Err(Error::TooLarge(size)) if size == Size::new(256, 64)
+    ));
+}
@@ -0,0 +1,100 @@
+use crate::Error;
+
+// Consistent Overhead Byte Stuffing: takes every zero out of a frame, at a cost of one byte per
+// 254, so a zero can mark where frames end. Each block starts with its length plus one and ends
+// where a zero was, except for blocks of the maximum length.
+
+fn put(out: &mut [u8], index: usize, byte: u8) -> Result<(), Error> {
+    *out.get_mut(index).ok_or(Error::BufferTooSmall)? = byte;
+    Ok(())
+}
+
+pub fn encode(data: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let mut code_index = 0;
+    let mut length = 1;
+    let mut code = 1;
+    for &byte in data {
+        if byte != 0 {
+            put(out, length, byte)?;
+            length += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xff {
+            put(out, code_index, code)?;
+            code_index = length;
+            length += 1;
+            code = 1;
+        }
+    }
+    put(out, code_index, code)?;
+    Ok(length)
+}
+
+pub fn decode(data: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let mut read = 0;
+    let mut length = 0;
+    while read < data.len() {
+        let code = data[read];
+        read += 1;
+        if code == 0 {
+            return Err(Error::Framing);
+        }
+        for _ in 1..code {
+            match data.get(read) {
+                Some(&byte) if byte != 0 => put(out, length, byte)?,
+                _ => return Err(Error::Framing),
+            }
+            read += 1;
+            length += 1;
+        }
+        if code != 0xff && read < data.len() {
+            put(out, length, 0)?;
+            length += 1;
+        }
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cobs::{decode, encode};
+    use crate::Error;
+
+    #[test]
+    fn test_encode() {
+        let cases: [(&[u8], &[u8]); 5] = [
+            (&[], &[0x01]),
+            (&[0x00], &[0x01, 0x01]),
+            (&[0x00, 0x00], &[0x01, 0x01, 0x01]),
+            (&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]),
+            (&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01]),
+        ];
+        for (data, encoded) in cases {
+            let mut out = [0; 8];
+            let length = encode(data, &mut out).unwrap();
+            assert_eq!(&out[..length], encoded);
+            let mut decoded = [0; 8];
+            let length = decode(encoded, &mut decoded).unwrap();
+            assert_eq!(&decoded[..length], data);
+        }
+    }
+
+    #[test]
+    fn test_long_blocks() {
+        let data: [u8; 600] = core::array::from_fn(|index| (index % 300) as u8);
+        let mut encoded = [0; 610];
+        let length = encode(&data, &mut encoded).unwrap();
+        assert!(!encoded[..length].contains(&0));
+        let mut decoded = [0; 600];
+        assert_eq!(decode(&encoded[..length], &mut decoded), Ok(600));
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut out = [0; 4];
+        assert_eq!(encode(&[1, 2, 3, 4], &mut out), Err(Error::BufferTooSmall));
+        assert_eq!(decode(&[0x03, 0x11], &mut out), Err(Error::Framing));
+        assert_eq!(decode(&[0x02, 0x00], &mut out), Err(Error::Framing));
+    }
+}
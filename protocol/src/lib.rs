@@ -0,0 +1,456 @@
+#![no_std]
+pub mod cobs;
+pub mod crc;
+
+use core::fmt;
+use program::abstract_device::{GameEvent, Inputs};
+use program::settings::{Difficulty, GameMode, Settings};
+
+// A frame is a message, its CRC and COBS framing, and ends with a zero byte. Messages start with a
+// tag byte and numbers are little endian.
+
+// Longest message, a shown sequence: tag, level, length and 16 bytes of symbols
+pub const MAX_MESSAGE: usize = 20;
+// Message, CRC, one byte of COBS overhead and the zero at the end
+pub const MAX_FRAME: usize = MAX_MESSAGE + 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Error {
+    BufferTooSmall,
+    // Bad COBS, or a frame longer than any message
+    Framing,
+    Crc,
+    UnknownMessage,
+    // A message too short or too long for its tag, or a value out of range
+    InvalidMessage,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::BufferTooSmall => "buffer too small",
+            Error::Framing => "invalid framing",
+            Error::Crc => "CRC mismatch",
+            Error::UnknownMessage => "unknown message",
+            Error::InvalidMessage => "invalid message",
+        })
+    }
+}
+
+impl core::error::Error for Error {}
+
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    length: usize,
+}
+
+impl Writer<'_> {
+    pub fn u8(&mut self, value: u8) -> Result<(), Error> {
+        *self.buffer.get_mut(self.length).ok_or(Error::BufferTooSmall)? = value;
+        self.length += 1;
+        Ok(())
+    }
+
+    pub fn bytes(&mut self, values: &[u8]) -> Result<(), Error> {
+        values.iter().try_for_each(|&value| self.u8(value))
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    pub fn u8(&mut self) -> Result<u8, Error> {
+        let (&first, rest) = self.bytes.split_first().ok_or(Error::InvalidMessage)?;
+        self.bytes = rest;
+        Ok(first)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        for byte in &mut array {
+            *byte = self.u8()?;
+        }
+        Ok(array)
+    }
+}
+
+pub trait Message: Sized {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error>;
+    fn read(reader: &mut Reader) -> Result<Self, Error>;
+}
+
+// Up to 128 symbols as bits, like the game's longest sequence
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Sequence {
+    length: u8,
+    bits: u128,
+}
+
+impl Sequence {
+    pub const MAX_LENGTH: usize = 128;
+
+    // Symbols past MAX_LENGTH are left out
+    pub fn new(sequence: &[bool]) -> Self {
+        let sequence = &sequence[..sequence.len().min(Self::MAX_LENGTH)];
+        let bits = sequence
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (index, &value)| bits | (value as u128) << index);
+        Sequence {
+            length: sequence.len() as u8,
+            bits,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len()).map(|index| self.bits >> index & 1 == 1)
+    }
+
+    fn byte_count(length: usize) -> usize {
+        length.div_ceil(8)
+    }
+}
+
+// Sent by the device
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DeviceMessage {
+    Pong,
+    GameStarted { settings: Settings },
+    SequenceShown { level: u16, sequence: Sequence },
+    SequenceHidden,
+    Input { index: u8, value: bool, correct: bool },
+    GameOver { score: f32 },
+}
+
+impl From<GameEvent<'_>> for DeviceMessage {
+    fn from(event: GameEvent) -> Self {
+        match event {
+            GameEvent::GameStarted { settings, .. } => DeviceMessage::GameStarted { settings },
+            GameEvent::SequenceShown { level, sequence } => DeviceMessage::SequenceShown {
+                level: level.min(u16::MAX as usize) as u16,
+                sequence: Sequence::new(sequence),
+            },
+            GameEvent::SequenceHidden => DeviceMessage::SequenceHidden,
+            GameEvent::Input { index, value, correct } => DeviceMessage::Input {
+                index: index.min(u8::MAX as usize) as u8,
+                value,
+                correct,
+            },
+            GameEvent::GameOver { score } => DeviceMessage::GameOver { score },
+        }
+    }
+}
+
+// Sent by the host
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HostMessage {
+    Ping,
+    // Buttons held on the host, on top of the device's own. They stay held until the next Inputs.
+    Inputs(Inputs),
+    // Applies from the next game, like settings changed on the device
+    SetSettings(Settings),
+}
+
+fn write_settings(settings: Settings, writer: &mut Writer) -> Result<(), Error> {
+    writer.u8(settings.mode as u8)?;
+    writer.u8(settings.difficulty as u8)
+}
+
+fn read_settings(reader: &mut Reader) -> Result<Settings, Error> {
+    let mode = GameMode::ALL.get(reader.u8()? as usize);
+    let difficulty = Difficulty::ALL.get(reader.u8()? as usize);
+    match (mode, difficulty) {
+        (Some(&mode), Some(&difficulty)) => Ok(Settings { mode, difficulty }),
+        _ => Err(Error::InvalidMessage),
+    }
+}
+
+impl Message for DeviceMessage {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        match *self {
+            DeviceMessage::Pong => writer.u8(0x01),
+            DeviceMessage::GameStarted { settings } => {
+                writer.u8(0x10)?;
+                write_settings(settings, writer)
+            }
+            DeviceMessage::SequenceShown { level, sequence } => {
+                writer.u8(0x11)?;
+                writer.bytes(&level.to_le_bytes())?;
+                writer.u8(sequence.length)?;
+                let bytes = sequence.bits.to_le_bytes();
+                writer.bytes(&bytes[..Sequence::byte_count(sequence.len())])
+            }
+            DeviceMessage::SequenceHidden => writer.u8(0x12),
+            DeviceMessage::Input { index, value, correct } => {
+                writer.u8(0x13)?;
+                writer.u8(index)?;
+                writer.u8(value as u8 | (correct as u8) << 1)
+            }
+            DeviceMessage::GameOver { score } => {
+                writer.u8(0x14)?;
+                writer.bytes(&score.to_le_bytes())
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match reader.u8()? {
+            0x01 => DeviceMessage::Pong,
+            0x10 => DeviceMessage::GameStarted {
+                settings: read_settings(reader)?,
+            },
+            0x11 => {
+                let level = u16::from_le_bytes(reader.array()?);
+                let length = reader.u8()?;
+                if length as usize > Sequence::MAX_LENGTH {
+                    return Err(Error::InvalidMessage);
+                }
+                let mut bytes = [0; 16];
+                for byte in &mut bytes[..Sequence::byte_count(length as usize)] {
+                    *byte = reader.u8()?;
+                }
+                let bits = u128::from_le_bytes(bytes);
+                if length < 128 && bits >> length != 0 {
+                    return Err(Error::InvalidMessage);
+                }
+                DeviceMessage::SequenceShown {
+                    level,
+                    sequence: Sequence { length, bits },
+                }
+            }
+            0x12 => DeviceMessage::SequenceHidden,
+            0x13 => {
+                let index = reader.u8()?;
+                let flags = reader.u8()?;
+                if flags > 0b11 {
+                    return Err(Error::InvalidMessage);
+                }
+                DeviceMessage::Input {
+                    index,
+                    value: flags & 1 != 0,
+                    correct: flags & 2 != 0,
+                }
+            }
+            0x14 => DeviceMessage::GameOver {
+                score: f32::from_le_bytes(reader.array()?),
+            },
+            _ => return Err(Error::UnknownMessage),
+        })
+    }
+}
+
+impl Message for HostMessage {
+    fn write(&self, writer: &mut Writer) -> Result<(), Error> {
+        match *self {
+            HostMessage::Ping => writer.u8(0x01),
+            HostMessage::Inputs(inputs) => {
+                writer.u8(0x02)?;
+                writer.u8(inputs.button1_down as u8 | (inputs.button2_down as u8) << 1)
+            }
+            HostMessage::SetSettings(settings) => {
+                writer.u8(0x03)?;
+                write_settings(settings, writer)
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(match reader.u8()? {
+            0x01 => HostMessage::Ping,
+            0x02 => {
+                let buttons = reader.u8()?;
+                if buttons > 0b11 {
+                    return Err(Error::InvalidMessage);
+                }
+                HostMessage::Inputs(Inputs {
+                    button1_down: buttons & 1 != 0,
+                    button2_down: buttons & 2 != 0,
+                })
+            }
+            0x03 => HostMessage::SetSettings(read_settings(reader)?),
+            _ => return Err(Error::UnknownMessage),
+        })
+    }
+}
+
+// Encodes a message into a frame, returning the part of the buffer it takes up
+pub fn encode<'a, M: Message>(message: &M, frame: &'a mut [u8; MAX_FRAME]) -> Result<&'a [u8], Error> {
+    let mut buffer = [0; MAX_MESSAGE + 2];
+    let mut writer = Writer {
+        buffer: &mut buffer[..MAX_MESSAGE],
+        length: 0,
+    };
+    message.write(&mut writer)?;
+    let length = writer.length;
+    let crc = crc::crc16(&buffer[..length]);
+    buffer[length..length + 2].copy_from_slice(&crc.to_le_bytes());
+    let length = cobs::encode(&buffer[..length + 2], &mut frame[..MAX_FRAME - 1])?;
+    frame[length] = 0;
+    Ok(&frame[..length + 1])
+}
+
+fn decode<M: Message>(frame: &[u8]) -> Result<M, Error> {
+    let mut buffer = [0; MAX_MESSAGE + 2];
+    let length = cobs::decode(frame, &mut buffer).map_err(|_| Error::Framing)?;
+    let (message, crc) = buffer[..length]
+        .split_last_chunk::<2>()
+        .ok_or(Error::Framing)?;
+    if crc::crc16(message) != u16::from_le_bytes(*crc) {
+        return Err(Error::Crc);
+    }
+    let mut reader = Reader { bytes: message };
+    let message = M::read(&mut reader)?;
+    if !reader.bytes.is_empty() {
+        return Err(Error::InvalidMessage);
+    }
+    Ok(message)
+}
+
+// Collects received bytes into frames, for a stream that may start or break off anywhere
+pub struct Decoder {
+    frame: [u8; MAX_FRAME],
+    length: usize,
+    overflowed: bool,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder {
+            frame: [0; MAX_FRAME],
+            length: 0,
+            overflowed: false,
+        }
+    }
+
+    // Returns a message, or why it couldn't be read, at the end of each frame
+    pub fn push<M: Message>(&mut self, byte: u8) -> Option<Result<M, Error>> {
+        if byte != 0 {
+            match self.frame.get_mut(self.length) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.length += 1;
+                }
+                None => self.overflowed = true,
+            }
+            return None;
+        }
+        let frame = &self.frame[..self.length];
+        let result = match (self.overflowed, frame.is_empty()) {
+            (true, _) => Some(Err(Error::Framing)),
+            // Zeros between frames are allowed, so a sender can start with one to resynchronise
+            (false, true) => None,
+            (false, false) => Some(decode(frame)),
+        };
+        self.length = 0;
+        self.overflowed = false;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encode, Decoder, DeviceMessage, Error, HostMessage, Message, Sequence, MAX_FRAME};
+    use program::abstract_device::{GameEvent, Inputs};
+    use program::settings::{Difficulty, GameMode, Settings};
+
+    fn round_trip<M: Message + PartialEq + core::fmt::Debug + Copy>(messages: &[M]) {
+        let mut decoder = Decoder::new();
+        for message in messages {
+            let mut frame = [0; MAX_FRAME];
+            let frame = encode(message, &mut frame).unwrap();
+            assert_eq!(frame.iter().position(|&byte| byte == 0), Some(frame.len() - 1));
+            let (last, rest) = frame.split_last().unwrap();
+            for &byte in rest {
+                assert!(decoder.push::<M>(byte).is_none());
+            }
+            assert_eq!(decoder.push(*last), Some(Ok(*message)));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let long: [bool; 128] = core::array::from_fn(|index| index % 3 == 0);
+        let sequence = Sequence::new(&[false, false, true]);
+        assert!(sequence.iter().eq([false, false, true]));
+        round_trip(&[
+            DeviceMessage::Pong,
+            DeviceMessage::GameStarted {
+                settings: Settings {
+                    mode: GameMode::Lives,
+                    difficulty: Difficulty::Hard,
+                },
+            },
+            DeviceMessage::SequenceShown { level: 300, sequence },
+            DeviceMessage::SequenceShown { level: 1, sequence: Sequence::new(&long) },
+            DeviceMessage::SequenceShown { level: 1, sequence: Sequence::new(&[]) },
+            DeviceMessage::SequenceHidden,
+            DeviceMessage::Input { index: 2, value: true, correct: false },
+            DeviceMessage::GameOver { score: 4.5 },
+        ]);
+        round_trip(&[
+            HostMessage::Ping,
+            HostMessage::Inputs(Inputs {
+                button1_down: false,
+                button2_down: true,
+            }),
+            HostMessage::SetSettings(Settings::default()),
+        ]);
+    }
+
+    #[test]
+    fn test_from_event() {
+        let event = GameEvent::SequenceShown {
+            level: 2,
+            sequence: &[true, false, true, true],
+        };
+        let DeviceMessage::SequenceShown { level, sequence } = DeviceMessage::from(event) else {
+            panic!("wrong message");
+        };
+        assert_eq!(level, 2);
+        assert!(sequence.iter().eq([true, false, true, true]));
+    }
+
+    // The result of the last frame that ended in the bytes
+    fn push_all(decoder: &mut Decoder, bytes: &[u8]) -> Option<Result<HostMessage, Error>> {
+        bytes.iter().fold(None, |last, &byte| decoder.push(byte).or(last))
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut decoder = Decoder::new();
+        let mut frame = [0; MAX_FRAME];
+
+        // A flipped bit is caught by the CRC
+        let mut bad = [0; MAX_FRAME];
+        let length = encode(&HostMessage::Ping, &mut frame).unwrap().len();
+        bad[..length].copy_from_slice(&frame[..length]);
+        bad[1] ^= 0x04;
+        assert_eq!(push_all(&mut decoder, &bad[..length]), Some(Err(Error::Crc)));
+
+        // Device messages aren't host messages
+        let sent = encode(&DeviceMessage::SequenceHidden, &mut frame).unwrap();
+        assert_eq!(push_all(&mut decoder, sent), Some(Err(Error::UnknownMessage)));
+
+        // A frame longer than any message is dropped, and the next one still decodes
+        assert_eq!(push_all(&mut decoder, &[0x55; 40]), None);
+        assert_eq!(push_all(&mut decoder, &[0]), Some(Err(Error::Framing)));
+        let sent = encode(&HostMessage::Ping, &mut frame).unwrap();
+        assert_eq!(push_all(&mut decoder, sent), Some(Ok(HostMessage::Ping)));
+    }
+}
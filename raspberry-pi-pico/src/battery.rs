@@ -0,0 +1,55 @@
+// The battery voltage, which the Pico and the Pico 2 divide by 3 from VSYS onto GPIO29, ADC
+// channel 3. On USB, VSYS is above a full battery, so the level reads as full.
+use crate::board;
+use crate::hal;
+use cortex_m::prelude::_embedded_hal_adc_OneShot;
+use hal::adc::AdcPin;
+use hal::gpio::{FunctionSioInput, Pin, PullNone};
+use hal::Adc;
+
+// A single LiPo cell, empty well above the 1.8 V the Pico's regulator still runs from
+const EMPTY_MV: u32 = 3300;
+const FULL_MV: u32 = 4200;
+// Averaged, as single readings of VSYS jump by tens of mV
+const SAMPLES: u32 = 8;
+// The level is saved to flash when it moved this far from the saved one, so after the battery ran
+// out the last level it had is there
+const SAVE_STEP: u8 = 10;
+
+pub type VsysPin = AdcPin<Pin<board::Vsys, FunctionSioInput, PullNone>>;
+
+pub struct Battery {
+    adc: Adc,
+    pin: VsysPin,
+    saved: Option<u8>,
+}
+
+impl Battery {
+    // `saved` is the level in flash from before this boot
+    pub fn new(adc: Adc, pin: VsysPin, saved: Option<u8>) -> Self {
+        Battery { adc, pin, saved }
+    }
+
+    pub fn level(&mut self) -> u8 {
+        let mut total = 0;
+        for _ in 0..SAMPLES {
+            let sample: u16 = self.adc.read(&mut self.pin).unwrap();
+            total += sample as u32;
+        }
+        // 12 bit readings of a third of VSYS against 3.3 V
+        let mv = total * 3300 * 3 / 4096 / SAMPLES;
+        let above_empty = mv.clamp(EMPTY_MV, FULL_MV) - EMPTY_MV;
+        (above_empty * 100 / (FULL_MV - EMPTY_MV)) as u8
+    }
+
+    // Whether `level` should be saved to flash, which it then counts as
+    pub fn should_save(&mut self, level: u8) -> bool {
+        match self.saved {
+            Some(saved) if saved.abs_diff(level) < SAVE_STEP => false,
+            _ => {
+                self.saved = Some(level);
+                true
+            }
+        }
+    }
+}
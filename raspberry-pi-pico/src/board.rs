@@ -0,0 +1,275 @@
+// What is wired to which pin on each board the firmware runs on, picked with a cargo feature
+// (`board-pico`, `board-pro-micro` or `board-pico2`). The rest of the firmware only gets its pins
+// from `split`, so supporting another board is another module here.
+use crate::hal::gpio::{FunctionNull, Pin, PullDown};
+
+#[cfg(not(any(
+    all(
+        feature = "board-pico",
+        not(feature = "board-pro-micro"),
+        not(feature = "board-pico2")
+    ),
+    all(
+        feature = "board-pro-micro",
+        not(feature = "board-pico"),
+        not(feature = "board-pico2")
+    ),
+    all(
+        feature = "board-pico2",
+        not(feature = "board-pico"),
+        not(feature = "board-pro-micro")
+    )
+)))]
+compile_error!("Enable exactly one board feature: board-pico, board-pro-micro or board-pico2");
+
+#[cfg(feature = "board-pico")]
+pub use pico::*;
+#[cfg(feature = "board-pico2")]
+pub use pico2::*;
+#[cfg(feature = "board-pro-micro")]
+pub use pro_micro::*;
+
+// A pin as the BSP hands it out, before it is configured for anything
+pub type Unconfigured<Id> = Pin<Id, FunctionNull, PullDown>;
+
+pub struct BoardPins {
+    pub uart_tx: Unconfigured<UartTx>,
+    pub uart_rx: Unconfigured<UartRx>,
+    pub led: Led,
+    pub button1: Unconfigured<Button1>,
+    pub button2: Unconfigured<Button2>,
+    // Left floating, so its ADC readings are noise for the RNG seed
+    pub seed_adc: Unconfigured<SeedAdc>,
+    // VSYS divided by 3, on boards that measure it, see battery.rs
+    pub vsys: Option<Unconfigured<Vsys>>,
+    // The data pin of WS2812 RGB LEDs, see ws2812.rs
+    pub rgb_led: Unconfigured<RgbLed>,
+    pub display: DisplayPins,
+}
+
+// The display's bus, see display::bus
+pub struct DisplayPins {
+    #[cfg(feature = "i2c")]
+    pub sda: Unconfigured<Sda>,
+    #[cfg(feature = "i2c")]
+    pub scl: Unconfigured<Scl>,
+    #[cfg(feature = "spi")]
+    pub sck: Unconfigured<Sck>,
+    #[cfg(feature = "spi")]
+    pub mosi: Unconfigured<Mosi>,
+    #[cfg(feature = "spi")]
+    pub cs: Unconfigured<Cs>,
+    #[cfg(feature = "spi")]
+    pub dc: Unconfigured<Dc>,
+    #[cfg(feature = "spi")]
+    pub reset: Unconfigured<Reset>,
+}
+
+// The Raspberry Pi Pico, with its green LED
+#[cfg(feature = "board-pico")]
+mod pico {
+    use super::{BoardPins, DisplayPins};
+    use crate::hal::gpio::{bank0, FunctionSioOutput, Pin, PullDown};
+    pub use rp_pico as bsp;
+    pub use rp_pico::{Pins, XOSC_CRYSTAL_FREQ};
+
+    pub type UartTx = bank0::Gpio0;
+    pub type UartRx = bank0::Gpio1;
+    pub type Led = Pin<bank0::Gpio25, FunctionSioOutput, PullDown>;
+    pub type Button1 = bank0::Gpio7;
+    pub type Button2 = bank0::Gpio8;
+    pub type SeedAdc = bank0::Gpio28;
+    pub type Vsys = bank0::Gpio29;
+    // For an external strip, nothing is on it by default
+    pub type RgbLed = bank0::Gpio22;
+    #[cfg(feature = "i2c")]
+    pub type Sda = bank0::Gpio20;
+    #[cfg(feature = "i2c")]
+    pub type Scl = bank0::Gpio21;
+    #[cfg(feature = "spi")]
+    pub type Sck = bank0::Gpio18;
+    #[cfg(feature = "spi")]
+    pub type Mosi = bank0::Gpio19;
+    #[cfg(feature = "spi")]
+    pub type Cs = bank0::Gpio17;
+    #[cfg(feature = "spi")]
+    pub type Dc = bank0::Gpio16;
+    #[cfg(feature = "spi")]
+    pub type Reset = bank0::Gpio20;
+
+    pub fn split(pins: Pins) -> BoardPins {
+        BoardPins {
+            uart_tx: pins.gpio0,
+            uart_rx: pins.gpio1,
+            led: pins.led.into_push_pull_output(),
+            button1: pins.gpio7,
+            button2: pins.gpio8,
+            seed_adc: pins.gpio28,
+            vsys: Some(pins.voltage_monitor),
+            rgb_led: pins.gpio22,
+            display: DisplayPins {
+                #[cfg(feature = "i2c")]
+                sda: pins.gpio20,
+                #[cfg(feature = "i2c")]
+                scl: pins.gpio21,
+                #[cfg(feature = "spi")]
+                sck: pins.gpio18,
+                #[cfg(feature = "spi")]
+                mosi: pins.gpio19,
+                #[cfg(feature = "spi")]
+                cs: pins.gpio17,
+                #[cfg(feature = "spi")]
+                dc: pins.gpio16,
+                #[cfg(feature = "spi")]
+                reset: pins.gpio20,
+            },
+        }
+    }
+}
+
+// The Raspberry Pi Pico 2, with the same pinout as the Pico and so the same pin map. The HAL has
+// no BSP for it, its pins are the HAL's.
+#[cfg(feature = "board-pico2")]
+mod pico2 {
+    use super::{BoardPins, DisplayPins};
+    pub use crate::hal::gpio::Pins;
+    use crate::hal::gpio::{bank0, FunctionSioOutput, Pin, PullDown};
+
+    // External high-speed crystal on the Pico 2 board is 12Mhz
+    pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+    pub type UartTx = bank0::Gpio0;
+    pub type UartRx = bank0::Gpio1;
+    pub type Led = Pin<bank0::Gpio25, FunctionSioOutput, PullDown>;
+    pub type Button1 = bank0::Gpio7;
+    pub type Button2 = bank0::Gpio8;
+    pub type SeedAdc = bank0::Gpio28;
+    pub type Vsys = bank0::Gpio29;
+    // For an external strip, nothing is on it by default
+    pub type RgbLed = bank0::Gpio22;
+    #[cfg(feature = "i2c")]
+    pub type Sda = bank0::Gpio20;
+    #[cfg(feature = "i2c")]
+    pub type Scl = bank0::Gpio21;
+    #[cfg(feature = "spi")]
+    pub type Sck = bank0::Gpio18;
+    #[cfg(feature = "spi")]
+    pub type Mosi = bank0::Gpio19;
+    #[cfg(feature = "spi")]
+    pub type Cs = bank0::Gpio17;
+    #[cfg(feature = "spi")]
+    pub type Dc = bank0::Gpio16;
+    #[cfg(feature = "spi")]
+    pub type Reset = bank0::Gpio20;
+
+    pub fn split(pins: Pins) -> BoardPins {
+        BoardPins {
+            uart_tx: pins.gpio0,
+            uart_rx: pins.gpio1,
+            led: pins.gpio25.into_push_pull_output(),
+            button1: pins.gpio7,
+            button2: pins.gpio8,
+            seed_adc: pins.gpio28,
+            vsys: Some(pins.gpio29),
+            rgb_led: pins.gpio22,
+            display: DisplayPins {
+                #[cfg(feature = "i2c")]
+                sda: pins.gpio20,
+                #[cfg(feature = "i2c")]
+                scl: pins.gpio21,
+                #[cfg(feature = "spi")]
+                sck: pins.gpio18,
+                #[cfg(feature = "spi")]
+                mosi: pins.gpio19,
+                #[cfg(feature = "spi")]
+                cs: pins.gpio17,
+                #[cfg(feature = "spi")]
+                dc: pins.gpio16,
+                #[cfg(feature = "spi")]
+                reset: pins.gpio20,
+            },
+        }
+    }
+}
+
+// The SparkFun Pro Micro RP2040. Its only LED is an addressable RGB one, so there is no LED to
+// blink but the RGB LED shows the game's colours and error codes, and an I2C display plugs into
+// the Qwiic connector.
+#[cfg(feature = "board-pro-micro")]
+mod pro_micro {
+    use super::{BoardPins, DisplayPins};
+    use crate::hal::gpio::bank0;
+    use core::convert::Infallible;
+    use embedded_hal::digital::{ErrorType, OutputPin};
+    pub use sparkfun_pro_micro_rp2040 as bsp;
+    pub use sparkfun_pro_micro_rp2040::{Pins, XOSC_CRYSTAL_FREQ};
+
+    pub type UartTx = bank0::Gpio0;
+    pub type UartRx = bank0::Gpio1;
+    pub type Led = NoLed;
+    pub type Button1 = bank0::Gpio7;
+    pub type Button2 = bank0::Gpio8;
+    pub type SeedAdc = bank0::Gpio28;
+    // A plain header pin, the board doesn't divide VSYS onto it
+    pub type Vsys = bank0::Gpio29;
+    // The one on the board
+    pub type RgbLed = bank0::Gpio25;
+    #[cfg(feature = "i2c")]
+    pub type Sda = bank0::Gpio16;
+    #[cfg(feature = "i2c")]
+    pub type Scl = bank0::Gpio17;
+    #[cfg(feature = "spi")]
+    pub type Sck = bank0::Gpio22;
+    #[cfg(feature = "spi")]
+    pub type Mosi = bank0::Gpio23;
+    #[cfg(feature = "spi")]
+    pub type Cs = bank0::Gpio21;
+    #[cfg(feature = "spi")]
+    pub type Dc = bank0::Gpio20;
+    #[cfg(feature = "spi")]
+    pub type Reset = bank0::Gpio2;
+
+    pub struct NoLed;
+
+    impl ErrorType for NoLed {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for NoLed {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    pub fn split(pins: Pins) -> BoardPins {
+        BoardPins {
+            uart_tx: pins.tx0,
+            uart_rx: pins.rx0,
+            led: NoLed,
+            button1: pins.gpio7,
+            button2: pins.tx1,
+            seed_adc: pins.adc2,
+            vsys: None,
+            rgb_led: pins.led,
+            display: DisplayPins {
+                #[cfg(feature = "i2c")]
+                sda: pins.sda,
+                #[cfg(feature = "i2c")]
+                scl: pins.scl,
+                #[cfg(feature = "spi")]
+                sck: pins.sck,
+                #[cfg(feature = "spi")]
+                mosi: pins.copi,
+                #[cfg(feature = "spi")]
+                cs: pins.ncs,
+                #[cfg(feature = "spi")]
+                dc: pins.cipo,
+                #[cfg(feature = "spi")]
+                reset: pins.gpio2,
+            },
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// What the shared modules need that is different on the RP2040 and the RP2350, for the chip the
+// board feature picked
+use crate::board;
+use crate::hal;
+use crate::uart_link::UartLink;
+use hal::gpio::{FunctionUart, Pin, PullDown};
+use hal::pac;
+
+#[cfg(feature = "rp2040")]
+pub use rp2040::*;
+#[cfg(feature = "rp2350")]
+pub use rp2350::*;
+
+pub type HostLink = UartLink<
+    pac::UART0,
+    (
+        Pin<board::UartTx, FunctionUart, PullDown>,
+        Pin<board::UartRx, FunctionUart, PullDown>,
+    ),
+>;
+
+#[cfg(feature = "rp2040")]
+mod rp2040 {
+    use crate::hal;
+    use cortex_m::peripheral::syst::SystClkSource;
+    use cortex_m::peripheral::SYST;
+    use hal::clocks::ClocksManager;
+    use hal::pac;
+    #[cfg(not(feature = "i2c-dma"))]
+    use hal::{
+        multicore::{Multicore, Stack},
+        sio::SioFifo,
+    };
+
+    const SYST_MAX: u32 = 0xff_ffff;
+
+    // Core1 only sends frames to the display, see flusher.rs
+    #[cfg(not(feature = "i2c-dma"))]
+    static mut CORE1_STACK: Stack<4096> = Stack::new();
+
+    // What init_clocks_and_plls sets the system clock to
+    pub const SYSTEM_CLOCK_MHZ: u64 = 125;
+
+    pub type Timer = hal::Timer;
+
+    pub fn timer(timer: pac::TIMER, resets: &mut pac::RESETS, clocks: &ClocksManager) -> Timer {
+        hal::Timer::new(timer, resets, clocks)
+    }
+
+    // Counts the system clock's cycles with SysTick, as the Cortex-M0+ has no DWT cycle counter.
+    // SysTick counts down from 2^24 and wraps every 134 ms, far longer than a flush takes.
+    pub struct CycleCounter(SYST);
+
+    impl CycleCounter {
+        pub fn new(core: cortex_m::Peripherals) -> Self {
+            let mut syst = core.SYST;
+            syst.set_clock_source(SystClkSource::Core);
+            syst.set_reload(SYST_MAX);
+            syst.clear_current();
+            syst.enable_counter();
+            CycleCounter(syst)
+        }
+
+        pub fn now(&self) -> u32 {
+            SYST::get_current()
+        }
+
+        pub fn since(&self, start: u32) -> u32 {
+            start.wrapping_sub(SYST::get_current()) & SYST_MAX
+        }
+    }
+
+    // Taking the PSM makes this the only call, so nothing else refers to core1's stack
+    #[cfg(not(feature = "i2c-dma"))]
+    pub fn spawn_core1(
+        mut psm: pac::PSM,
+        mut ppb: pac::PPB,
+        fifo: &mut SioFifo,
+        entry: impl FnOnce() + Send + 'static,
+    ) {
+        let mut multicore = Multicore::new(&mut psm, &mut ppb, fifo);
+        let stack = unsafe { &mut *core::ptr::addr_of_mut!(CORE1_STACK.mem) };
+        multicore.cores()[1].spawn(stack, entry).unwrap();
+    }
+
+    pub fn reset_to_bootloader() {
+        hal::rom_data::reset_to_usb_boot(0, 0);
+    }
+}
+
+#[cfg(feature = "rp2350")]
+mod rp2350 {
+    use crate::hal;
+    use cortex_m::peripheral::DWT;
+    use hal::clocks::ClocksManager;
+    use hal::pac;
+    use hal::reboot::{RebootArch, RebootKind};
+    #[cfg(not(feature = "i2c-dma"))]
+    use hal::{
+        multicore::{Multicore, Stack},
+        sio::SioFifo,
+    };
+
+    // Core1 only sends frames to the display, see flusher.rs
+    #[cfg(not(feature = "i2c-dma"))]
+    static CORE1_STACK: Stack<4096> = Stack::new();
+
+    // What init_clocks_and_plls sets the system clock to
+    pub const SYSTEM_CLOCK_MHZ: u64 = 150;
+
+    pub type Timer = hal::Timer<hal::timer::CopyableTimer0>;
+
+    pub fn timer(timer: pac::TIMER0, resets: &mut pac::RESETS, clocks: &ClocksManager) -> Timer {
+        hal::Timer::new_timer0(timer, resets, clocks)
+    }
+
+    // Counts the system clock's cycles with the Cortex-M33's DWT cycle counter
+    pub struct CycleCounter;
+
+    impl CycleCounter {
+        pub fn new(mut core: cortex_m::Peripherals) -> Self {
+            core.DCB.enable_trace();
+            core.DWT.enable_cycle_counter();
+            CycleCounter
+        }
+
+        pub fn now(&self) -> u32 {
+            DWT::cycle_count()
+        }
+
+        pub fn since(&self, start: u32) -> u32 {
+            DWT::cycle_count().wrapping_sub(start)
+        }
+    }
+
+    #[cfg(not(feature = "i2c-dma"))]
+    pub fn spawn_core1(
+        mut psm: pac::PSM,
+        mut ppb: pac::PPB,
+        fifo: &mut SioFifo,
+        entry: impl FnOnce() + Send + 'static,
+    ) {
+        let mut multicore = Multicore::new(&mut psm, &mut ppb, fifo);
+        let stack = CORE1_STACK.take().unwrap();
+        multicore.cores()[1].spawn(stack, entry).unwrap();
+    }
+
+    pub fn reset_to_bootloader() {
+        let kind = RebootKind::BootSel {
+            picoboot_disabled: false,
+            msd_disabled: false,
+        };
+        hal::reboot::reboot(kind, RebootArch::Normal);
+    }
+
+    // 192 bits from the true random number generator, which the HAL has no driver for. A better
+    // source than the seed the RP2040 keeps in flash for the next boot, so the RP2350 keeps none.
+    pub fn read_trng(trng: pac::TRNG, resets: &mut pac::RESETS) -> [u32; 6] {
+        resets.reset().modify(|_, w| w.trng().clear_bit());
+        while resets.reset_done().read().trng().bit_is_clear() {}
+        trng.rnd_source_enable().write(|w| w.rnd_src_en().set_bit());
+        while trng.trng_valid().read().ehr_valid().bit_is_clear() {}
+        let bits = [
+            trng.ehr_data0().read().bits(),
+            trng.ehr_data1().read().bits(),
+            trng.ehr_data2().read().bits(),
+            trng.ehr_data3().read().bits(),
+            trng.ehr_data4().read().bits(),
+            trng.ehr_data5().read().bits(),
+        ];
+        trng.rnd_source_enable()
+            .write(|w| w.rnd_src_en().clear_bit());
+        bits
+    }
+}
@@ -0,0 +1,263 @@
+use core::convert::Infallible;
+use core::fmt;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::primitives::Rectangle;
+use embedded_hal::digital::{InputPin, OutputPin};
+use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+use program::framebuffer::{Framebuffer, TooLarge};
+use program::seed::SeedMixer;
+use program::settings::Settings;
+
+// What the device needs from the chip besides the buttons and the LED, see peripherals.rs
+pub trait Hardware {
+    type Error: From<Infallible> + From<fmt::Error> + From<TooLarge>;
+    // Called at least every ms, to feed the watchdog and answer the hosts
+    fn poll(&mut self);
+    // The buttons the hosts hold down
+    fn remote_inputs(&self) -> Inputs;
+    fn delay_ms(&mut self, ms: u32);
+    // A free running count, the time the player took is entropy for the seed
+    fn ticks(&mut self) -> u64;
+    // May return before the frame is on the display. Only `area` changed since the last frame.
+    fn flush(&mut self, frame: &Framebuffer, area: Rectangle) -> Result<(), Self::Error>;
+    // Kept for the next boot, where the chip can store it
+    fn store_seed(&mut self, seed: u64);
+    fn battery_level(&mut self) -> Option<u8>;
+    fn set_rgb_led(&mut self, color: Rgb888);
+    fn on_event(&mut self, event: GameEvent);
+    fn take_settings(&mut self) -> Option<Settings>;
+}
+
+// The game's view of the board, the same for every chip and board. The buttons pull their pins low.
+pub struct Device<Button1Pin, Button2Pin, LedPin, H> {
+    pub display_storage: Framebuffer,
+    pub button1_pin: Button1Pin,
+    pub button2_pin: Button2Pin,
+    pub led_pin: LedPin,
+    pub seed: SeedMixer,
+    pub hardware: H,
+}
+
+impl<Button1Pin: InputPin, Button2Pin: InputPin, LedPin: OutputPin, H: Hardware> AbstractDevice
+    for Device<Button1Pin, Button2Pin, LedPin, H>
+{
+    type Display = Framebuffer;
+    type Error = H::Error;
+    fn get_inputs(&mut self) -> Result<Inputs, Self::Error> {
+        self.hardware.poll();
+        let remote = self.hardware.remote_inputs();
+        Ok(Inputs {
+            button1_down: self.button1_pin.is_low().unwrap() || remote.button1_down,
+            button2_down: self.button2_pin.is_low().unwrap() || remote.button2_down,
+        })
+    }
+    fn set_led(&mut self, new_state: bool) {
+        if new_state {
+            self.led_pin.set_high().unwrap();
+        } else {
+            self.led_pin.set_low().unwrap();
+        }
+    }
+    fn delay_ms(&mut self, ms: u32) {
+        // A ms at a time, so the USB host keeps getting answers
+        for _ in 0..ms {
+            self.hardware.delay_ms(1);
+            self.hardware.poll();
+        }
+    }
+    fn get_rng_seed(&mut self) -> u64 {
+        // Asked for when the first game starts, so this is when the player pressed both buttons
+        self.seed.add(self.hardware.ticks());
+        let seed = self.seed.finish();
+        self.hardware.store_seed(seed);
+        seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display_storage
+    }
+    fn flush_display(&mut self, area: Rectangle) -> Result<(), Self::Error> {
+        self.hardware.flush(&self.display_storage, area)
+    }
+
+    fn on_event(&mut self, event: GameEvent) {
+        self.hardware.on_event(event);
+    }
+
+    fn take_settings(&mut self) -> Option<Settings> {
+        self.hardware.take_settings()
+    }
+
+    fn battery_level(&mut self) -> Option<u8> {
+        self.hardware.battery_level()
+    }
+
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.hardware.set_rgb_led(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use crate::device::{Device, Hardware};
+    use core::convert::Infallible;
+    use core::fmt;
+    use embedded_graphics::geometry::{Point, Size};
+    use embedded_graphics::pixelcolor::Rgb888;
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction};
+    use program::abstract_device::{AbstractDevice, GameEvent, Inputs};
+    use program::framebuffer::{Framebuffer, TooLarge};
+    use program::seed::SeedMixer;
+    use program::settings::Settings;
+    use std::vec::Vec;
+
+    #[derive(Debug)]
+    struct Error;
+
+    impl From<Infallible> for Error {
+        fn from(value: Infallible) -> Self {
+            match value {}
+        }
+    }
+
+    impl From<fmt::Error> for Error {
+        fn from(_value: fmt::Error) -> Self {
+            Error
+        }
+    }
+
+    impl From<TooLarge> for Error {
+        fn from(_value: TooLarge) -> Self {
+            Error
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeHardware {
+        polls: u32,
+        remote: Inputs,
+        delayed_ms: u32,
+        stored_seeds: Vec<u64>,
+        flushed_areas: Vec<Rectangle>,
+    }
+
+    impl Hardware for FakeHardware {
+        type Error = Error;
+        fn poll(&mut self) {
+            self.polls += 1;
+        }
+        fn remote_inputs(&self) -> Inputs {
+            self.remote
+        }
+        fn delay_ms(&mut self, ms: u32) {
+            self.delayed_ms += ms;
+        }
+        fn ticks(&mut self) -> u64 {
+            1234
+        }
+        fn flush(&mut self, _frame: &Framebuffer, area: Rectangle) -> Result<(), Self::Error> {
+            self.flushed_areas.push(area);
+            Ok(())
+        }
+        fn store_seed(&mut self, seed: u64) {
+            self.stored_seeds.push(seed);
+        }
+        fn battery_level(&mut self) -> Option<u8> {
+            Some(50)
+        }
+        fn set_rgb_led(&mut self, _color: Rgb888) {}
+        fn on_event(&mut self, _event: GameEvent) {}
+        fn take_settings(&mut self) -> Option<Settings> {
+            None
+        }
+    }
+
+    fn device(
+        button1: &[Transaction],
+        button2: &[Transaction],
+        led: &[Transaction],
+    ) -> Device<PinMock, PinMock, PinMock, FakeHardware> {
+        Device {
+            display_storage: Framebuffer::new(Size::new(128, 32)).unwrap(),
+            button1_pin: PinMock::new(button1),
+            button2_pin: PinMock::new(button2),
+            led_pin: PinMock::new(led),
+            seed: SeedMixer::new(),
+            hardware: FakeHardware::default(),
+        }
+    }
+
+    fn done(mut device: Device<PinMock, PinMock, PinMock, FakeHardware>) -> FakeHardware {
+        device.button1_pin.done();
+        device.button2_pin.done();
+        device.led_pin.done();
+        device.hardware
+    }
+
+    #[test]
+    fn test_buttons_are_pressed_when_low() {
+        let mut device = device(
+            &[Transaction::get(State::Low), Transaction::get(State::High)],
+            &[Transaction::get(State::High), Transaction::get(State::Low)],
+            &[],
+        );
+        let inputs = device.get_inputs().unwrap();
+        assert!(inputs.button1_down);
+        assert!(!inputs.button2_down);
+        let inputs = device.get_inputs().unwrap();
+        assert!(!inputs.button1_down);
+        assert!(inputs.button2_down);
+        assert_eq!(done(device).polls, 2);
+    }
+
+    #[test]
+    fn test_remote_inputs_add_to_buttons() {
+        let mut device = device(
+            &[Transaction::get(State::High)],
+            &[Transaction::get(State::High)],
+            &[],
+        );
+        device.hardware.remote = Inputs {
+            button1_down: false,
+            button2_down: true,
+        };
+        let inputs = device.get_inputs().unwrap();
+        assert!(!inputs.button1_down);
+        assert!(inputs.button2_down);
+        done(device);
+    }
+
+    #[test]
+    fn test_led() {
+        let mut device = device(
+            &[],
+            &[],
+            &[Transaction::set(State::High), Transaction::set(State::Low)],
+        );
+        device.set_led(true);
+        device.set_led(false);
+        done(device);
+    }
+
+    #[test]
+    fn test_delay_polls_every_ms() {
+        let mut device = device(&[], &[], &[]);
+        device.delay_ms(5);
+        let hardware = done(device);
+        assert_eq!(hardware.delayed_ms, 5);
+        assert_eq!(hardware.polls, 5);
+    }
+
+    #[test]
+    fn test_seed_is_stored() {
+        let mut device = device(&[], &[], &[]);
+        let seed = device.get_rng_seed();
+        let area = Rectangle::new(Point::new(10, 9), Size::new(1, 1));
+        device.flush_display(area).unwrap();
+        let hardware = done(device);
+        assert_eq!(hardware.stored_seeds, [seed]);
+        assert_eq!(hardware.flushed_areas, [area]);
+    }
+}
@@ -0,0 +1,174 @@
+// The display the firmware drives is picked with cargo features: the controller (`ssd1306` or
+// `sh1106`), how it is wired to the Pico (`i2c` or `spi`) and its size (`size-128x32` or
+// `size-128x64`). The game fits its layout to whatever size the display reports.
+use crate::board;
+use crate::error::Error;
+use crate::firmware::Clocks;
+use crate::hal;
+use embedded_hal::delay::DelayNs;
+use hal::fugit::RateExtU32;
+use hal::gpio::{self, Pin};
+use hal::pac;
+#[cfg(all(feature = "ssd1306", feature = "spi"))]
+use ssd1306::prelude::DisplayRotation;
+
+#[cfg(not(any(
+    all(feature = "ssd1306", not(feature = "sh1106")),
+    all(feature = "sh1106", not(feature = "ssd1306"))
+)))]
+compile_error!("Enable exactly one display controller feature: ssd1306 or sh1106");
+#[cfg(not(any(
+    all(feature = "i2c", not(feature = "spi")),
+    all(feature = "spi", not(feature = "i2c"))
+)))]
+compile_error!("Enable exactly one display bus feature: i2c or spi");
+#[cfg(not(any(
+    all(feature = "size-128x32", not(feature = "size-128x64")),
+    all(feature = "size-128x64", not(feature = "size-128x32"))
+)))]
+compile_error!("Enable exactly one display size feature: size-128x32 or size-128x64");
+
+// I2C0, on the pins board.rs gives for SDA and SCL
+#[cfg(feature = "i2c")]
+pub type Bus = hal::I2C<
+    pac::I2C0,
+    (
+        Pin<board::Sda, gpio::FunctionI2C, gpio::PullUp>,
+        Pin<board::Scl, gpio::FunctionI2C, gpio::PullUp>,
+    ),
+>;
+
+// SPI0 with MOSI and SCK, plus chip select, data/command and reset pins, on the pins board.rs gives
+#[cfg(feature = "spi")]
+pub type Bus = (SpiBus, SpiPins);
+#[cfg(feature = "spi")]
+type SpiBus = hal::Spi<
+    hal::spi::Enabled,
+    pac::SPI0,
+    (
+        Pin<board::Mosi, gpio::FunctionSpi, gpio::PullDown>,
+        Pin<board::Sck, gpio::FunctionSpi, gpio::PullDown>,
+    ),
+>;
+#[cfg(feature = "spi")]
+type CsPin = Pin<board::Cs, gpio::FunctionSioOutput, gpio::PullDown>;
+#[cfg(feature = "spi")]
+type DcPin = Pin<board::Dc, gpio::FunctionSioOutput, gpio::PullDown>;
+
+#[cfg(feature = "spi")]
+pub struct SpiPins {
+    pub cs: CsPin,
+    pub dc: DcPin,
+    pub reset: Pin<board::Reset, gpio::FunctionSioOutput, gpio::PullDown>,
+}
+
+#[cfg(all(feature = "ssd1306", feature = "size-128x32"))]
+type Size = ssd1306::prelude::DisplaySize128x32;
+#[cfg(all(feature = "ssd1306", feature = "size-128x64"))]
+type Size = ssd1306::prelude::DisplaySize128x64;
+#[cfg(all(feature = "sh1106", feature = "size-128x32"))]
+const SIZE: sh1106::displaysize::DisplaySize = sh1106::displaysize::DisplaySize::Display128x32;
+#[cfg(all(feature = "sh1106", feature = "size-128x64"))]
+const SIZE: sh1106::displaysize::DisplaySize = sh1106::displaysize::DisplaySize::Display128x64;
+
+#[cfg(all(feature = "ssd1306", feature = "i2c"))]
+type Interface = ssd1306::prelude::I2CInterface<Bus>;
+#[cfg(all(feature = "ssd1306", feature = "spi"))]
+type Interface = ssd1306::prelude::SPIInterface<
+    embedded_hal_bus::spi::ExclusiveDevice<SpiBus, CsPin, embedded_hal_bus::spi::NoDelay>,
+    DcPin,
+>;
+#[cfg(all(feature = "sh1106", feature = "i2c"))]
+type Interface = sh1106::interface::I2cInterface<Bus>;
+#[cfg(all(feature = "sh1106", feature = "spi"))]
+type Interface = sh1106::interface::SpiInterface<SpiBus, DcPin, CsPin>;
+
+#[cfg(feature = "ssd1306")]
+pub type Display = ssd1306::Ssd1306<Interface, Size, ssd1306::mode::BufferedGraphicsMode<Size>>;
+#[cfg(feature = "sh1106")]
+pub type Display = sh1106::mode::GraphicsMode<Interface>;
+
+#[cfg(all(feature = "ssd1306", feature = "i2c"))]
+fn connect(bus: Bus, _delay: &mut impl DelayNs) -> Result<Display, Error> {
+    Ok(crate::screen::ssd1306_i2c(bus, Size {}))
+}
+
+#[cfg(all(feature = "sh1106", feature = "i2c"))]
+fn connect(bus: Bus, _delay: &mut impl DelayNs) -> Result<Display, Error> {
+    Ok(sh1106::Builder::new()
+        .with_size(SIZE)
+        .connect_i2c(bus)
+        .into())
+}
+
+#[cfg(feature = "spi")]
+fn connect((spi, mut pins): Bus, delay: &mut impl DelayNs) -> Result<Display, Error> {
+    use embedded_hal::digital::OutputPin;
+    // Both controllers need a reset pulse after power on when wired for SPI
+    pins.reset.set_high()?;
+    delay.delay_ms(1);
+    pins.reset.set_low()?;
+    delay.delay_ms(10);
+    pins.reset.set_high()?;
+    #[cfg(feature = "ssd1306")]
+    {
+        let device = embedded_hal_bus::spi::ExclusiveDevice::new_no_delay(spi, pins.cs)?;
+        let interface = ssd1306::prelude::SPIInterface::new(device, pins.dc);
+        Ok(
+            ssd1306::Ssd1306::new(interface, Size {}, DisplayRotation::Rotate0)
+                .into_buffered_graphics_mode(),
+        )
+    }
+    #[cfg(feature = "sh1106")]
+    Ok(sh1106::Builder::new()
+        .with_size(SIZE)
+        .connect_spi(spi, pins.dc, pins.cs)
+        .into())
+}
+
+// I2C0 at 400 kHz or SPI0 at 8 MHz, on the board's display pins
+#[cfg(feature = "i2c")]
+pub fn bus(
+    pins: board::DisplayPins,
+    i2c: pac::I2C0,
+    resets: &mut pac::RESETS,
+    clocks: &Clocks,
+) -> Bus {
+    hal::I2C::i2c0(
+        i2c,
+        pins.sda.reconfigure(),
+        pins.scl.reconfigure(),
+        400.kHz(),
+        resets,
+        clocks.system,
+    )
+}
+
+#[cfg(feature = "spi")]
+pub fn bus(
+    pins: board::DisplayPins,
+    spi: pac::SPI0,
+    resets: &mut pac::RESETS,
+    clocks: &Clocks,
+) -> Bus {
+    let spi = hal::Spi::new(spi, (pins.mosi.reconfigure(), pins.sck.reconfigure())).init(
+        resets,
+        clocks.peripheral,
+        8.MHz(),
+        embedded_hal::spi::MODE_0,
+    );
+    let pins = SpiPins {
+        cs: pins.cs.reconfigure(),
+        dc: pins.dc.reconfigure(),
+        reset: pins.reset.reconfigure(),
+    };
+    (spi, pins)
+}
+
+pub fn new(bus: Bus, delay: &mut impl DelayNs) -> Result<Display, Error> {
+    #[cfg(feature = "ssd1306")]
+    use ssd1306::mode::DisplayConfig;
+    let mut display = connect(bus, delay)?;
+    display.init()?;
+    Ok(display)
+}
@@ -0,0 +1,167 @@
+// Sends frames to an SSD1306 on I2C0 by DMA, picked with the `i2c-dma` feature instead of the core1
+// flusher. The area the game changed since the last frame is written as I2C0 commands into a buffer,
+// which the DMA feeds to the controller's TX FIFO while the game goes on. Only a new frame waits
+// for the one before it, and after a failed transfer the next frame is sent whole.
+use crate::display;
+use crate::error::Error;
+use crate::hal;
+use embedded_dma::ReadBuffer;
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::primitives::Rectangle;
+use hal::dma::{single_buffer, Channel, WriteTarget, CH0};
+use hal::pac;
+use pac::dma::ch::ch_ctrl_trig::TREQ_SEL_A;
+use program::framebuffer::{Framebuffer, MAX_HEIGHT, MAX_WIDTH, PAGE_HEIGHT};
+
+#[cfg(not(all(feature = "ssd1306", feature = "i2c")))]
+compile_error!("The i2c-dma feature needs the ssd1306 and i2c features");
+
+// The address I2CDisplayInterface::new uses
+const ADDRESS: u16 = 0x3c;
+// In IC_DATA_CMD, ends the I2C transaction after the byte
+const STOP: u32 = 1 << 9;
+// One transaction setting the column and page window, and one with the control byte and the pixels
+const HEADER_WORDS: usize = 8;
+const MAX_WORDS: usize = HEADER_WORDS + (MAX_WIDTH * MAX_HEIGHT / PAGE_HEIGHT) as usize;
+
+// I2C0's IC_DATA_CMD register, which the DMA writes one byte and its flags at a time to
+pub struct DataCmd;
+
+unsafe impl WriteTarget for DataCmd {
+    type TransmittedWord = u32;
+
+    fn tx_treq() -> Option<u8> {
+        Some(TREQ_SEL_A::I2C0_TX.into())
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let i2c = unsafe { &*pac::I2C0::ptr() };
+        (i2c.ic_data_cmd().as_ptr() as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+// The buffer, of which a transfer sends the first `len` words
+struct Words {
+    buffer: &'static mut [u32; MAX_WORDS],
+    len: usize,
+}
+
+// The buffer is 'static, so it stays put while the DMA reads it
+unsafe impl ReadBuffer for Words {
+    type Word = u32;
+
+    unsafe fn read_buffer(&self) -> (*const u32, usize) {
+        (self.buffer.as_ptr(), self.len)
+    }
+}
+
+enum State {
+    Idle(Channel<CH0>, Words),
+    Sending(single_buffer::Transfer<Channel<CH0>, Words, DataCmd>),
+}
+
+pub struct Flusher {
+    // Owns I2C0 and its pins, its registers are only used through pac::I2C0 below
+    _bus: display::Bus,
+    state: Option<State>,
+    // Whether the last transfer failed, so the display may show any part of the frames since
+    resend: bool,
+}
+
+impl Flusher {
+    // Takes over the bus from the display after display::new has initialized it
+    pub fn new(display: display::Display, channel: Channel<CH0>) -> Self {
+        let bus = display.release().release();
+        let i2c = unsafe { &*pac::I2C0::ptr() };
+        i2c.ic_enable().write(|w| w.enable().disabled());
+        i2c.ic_tar().write(|w| unsafe { w.ic_tar().bits(ADDRESS) });
+        i2c.ic_dma_tdlr().write(|w| unsafe { w.dmatdl().bits(8) });
+        i2c.ic_dma_cr().write(|w| w.tdmae().enabled());
+        i2c.ic_enable().write(|w| w.enable().enabled());
+
+        let buffer = cortex_m::singleton!(: [u32; MAX_WORDS] = [0; MAX_WORDS]).unwrap();
+        Flusher {
+            _bus: bus,
+            state: Some(State::Idle(channel, Words { buffer, len: 0 })),
+            resend: false,
+        }
+    }
+
+    // Waits for the previous frame's transfer, and returns its error if it failed
+    pub fn wait(&mut self) -> Result<(), Error> {
+        let (channel, words, result) = match self.state.take().unwrap() {
+            State::Idle(channel, words) => (channel, words, Ok(())),
+            State::Sending(transfer) => {
+                let (channel, words, _) = transfer.wait();
+                (channel, words, read_abort())
+            }
+        };
+        self.state = Some(State::Idle(channel, words));
+        self.resend |= result.is_err();
+        result
+    }
+
+    // After wait. Like the SSD1306 driver, only sends the columns and pages the changes are in.
+    pub fn send(&mut self, frame: &Framebuffer, area: Rectangle) {
+        let Some(State::Idle(channel, mut words)) = self.state.take() else {
+            unreachable!("send without wait");
+        };
+        let area = if core::mem::take(&mut self.resend) {
+            frame.bounding_box()
+        } else {
+            area
+        };
+        let bottom_right = area.bottom_right().unwrap();
+        let columns = area.top_left.x as usize..=bottom_right.x as usize;
+        let first_page = area.top_left.y as u32 / PAGE_HEIGHT;
+        let last_page = bottom_right.y as u32 / PAGE_HEIGHT;
+        let header = [
+            0x00,
+            0x21,
+            *columns.start() as u32,
+            *columns.end() as u32,
+            0x22,
+            first_page,
+            last_page | STOP,
+            0x40,
+        ];
+        words.buffer[..HEADER_WORDS].copy_from_slice(&header);
+        let bytes = (first_page..=last_page).flat_map(|page| &frame.page(page)[columns.clone()]);
+        words.len = HEADER_WORDS;
+        for &byte in bytes {
+            words.buffer[words.len] = byte as u32;
+            words.len += 1;
+        }
+        words.buffer[words.len - 1] |= STOP;
+        let transfer = single_buffer::Config::new(channel, words, DataCmd).start();
+        self.state = Some(State::Sending(transfer));
+    }
+
+    // Core1 isn't started and the DMA only reads RAM, so nothing needs to wait for the write
+    pub fn writing_flash(&mut self, write: impl FnOnce()) {
+        write();
+    }
+}
+
+// The controller empties and ignores its TX FIFO after an abort until it is cleared, so the rest of
+// that transfer went nowhere. The DMA is done once the last word is in the FIFO, so this waits for
+// the controller to send it, or to abort, first.
+fn read_abort() -> Result<(), Error> {
+    let i2c = unsafe { &*pac::I2C0::ptr() };
+    loop {
+        let status = i2c.ic_status().read();
+        if status.tfe().is_empty() && status.activity().is_inactive() {
+            break;
+        }
+    }
+    let reason = i2c.ic_tx_abrt_source().read().bits();
+    if reason != 0 {
+        i2c.ic_clr_tx_abrt().read();
+        return Err(Error::I2c(hal::i2c::Error::Abort(reason)));
+    }
+    Ok(())
+}
@@ -8,7 +8,7 @@ use defmt::Format;
 pub enum Error {
     // Error on the I2C bus
     #[cfg(target_arch = "arm")]
-    I2c(rp2040_hal::i2c::Error),
+    I2c(crate::hal::i2c::Error),
     // Error inside the display transport layer
     #[cfg(not(target_arch = "x86_64"))]
     Display(display_interface::DisplayError),
@@ -22,8 +22,8 @@ pub enum Error {
     Quit,
 }
 #[cfg(target_arch = "arm")]
-impl From<rp2040_hal::i2c::Error> for Error {
-    fn from(value: rp2040_hal::i2c::Error) -> Self {
+impl From<crate::hal::i2c::Error> for Error {
+    fn from(value: crate::hal::i2c::Error) -> Self {
         Self::I2c(value)
     }
 }
@@ -35,6 +35,14 @@ impl From<display_interface::DisplayError> for Error {
     }
 }
 
+// The display is larger than the frame the game draws into
+#[cfg(not(target_arch = "x86_64"))]
+impl From<program::framebuffer::TooLarge> for Error {
+    fn from(_value: program::framebuffer::TooLarge) -> Self {
+        Self::Display(display_interface::DisplayError::OutOfBoundsError)
+    }
+}
+
 #[cfg(target_arch = "x86_64")]
 impl From<Infallible> for Error {
     fn from(_value: Infallible) -> Self {
@@ -42,9 +50,38 @@ impl From<Infallible> for Error {
     }
 }
 
+// The SH1106 driver draws into a buffer that can't fail, and the Pico's pins can't either
+#[cfg(target_arch = "arm")]
+impl From<core::convert::Infallible> for Error {
+    fn from(value: core::convert::Infallible) -> Self {
+        match value {}
+    }
+}
+
+#[cfg(all(target_arch = "arm", feature = "sh1106"))]
+impl<CommE: Into<Error>, PinE> From<sh1106::Error<CommE, PinE>> for Error {
+    fn from(value: sh1106::Error<CommE, PinE>) -> Self {
+        match value {
+            sh1106::Error::Comm(error) => error.into(),
+            sh1106::Error::Pin(_) => Self::Display(display_interface::DisplayError::DCError),
+        }
+    }
+}
+
 impl From<core::fmt::Error> for Error {
     fn from(_value: core::fmt::Error) -> Self {
         Self::Format
     }
 }
 
+
+#[cfg(target_arch = "arm")]
+impl From<&Error> for program::error_code::ErrorCode {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::I2c(_) => Self::I2c,
+            Error::Display(_) => Self::Display,
+            Error::Format => Self::Format,
+        }
+    }
+}
@@ -0,0 +1,327 @@
+// What the firmware does once main.rs has taken the chip's peripherals, the same on the RP2040 and
+// the RP2350 apart from what chip.rs covers: setting up the clocks, the pins and the peripherals,
+// gathering entropy for the seed, the watchdog, the display and how frames get to it, the game,
+// and blinking the LEDs when it returns an error before the watchdog restarts the Pico.
+use crate::battery::Battery;
+use crate::board::{self, Unconfigured};
+use crate::chip;
+use crate::device::Device;
+use crate::display;
+#[cfg(feature = "i2c-dma")]
+use crate::dma_flusher;
+use crate::error::Error;
+#[cfg(not(feature = "i2c-dma"))]
+use crate::flusher;
+use crate::hal;
+use crate::peripherals::Peripherals;
+use crate::recovery::{self, Reset};
+use crate::storage;
+use crate::uart_link::UartLink;
+use crate::usb_console::UsbConsole;
+use crate::ws2812::Ws2812;
+use cortex_m::prelude::_embedded_hal_adc_OneShot;
+use defmt::info;
+use embedded_graphics::geometry::Dimensions;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use hal::adc::AdcPin;
+use hal::clocks::{init_clocks_and_plls, Clock};
+#[cfg(feature = "i2c-dma")]
+use hal::dma::DMAExt;
+use hal::fugit::{HertzU32, MicrosDurationU32, RateExtU32};
+use hal::pac;
+use hal::sio::Sio;
+#[cfg(not(feature = "i2c-dma"))]
+use hal::sio::SioFifo;
+use hal::uart::{DataBits, StopBits, UartConfig, UartPeripheral};
+use hal::usb::UsbBus;
+use hal::watchdog::Watchdog;
+use hal::Adc;
+use program::error_code::ErrorCode;
+use program::framebuffer::Framebuffer;
+use program::seed::SeedMixer;
+use program::settings::Settings;
+use usb_device::bus::UsbBusAllocator;
+
+// The longest the game goes without reading inputs or waiting is a flash erase, far less than this
+const WATCHDOG_TIMEOUT_MS: u32 = 2000;
+// How often the LEDs blink an error's code before the Pico restarts
+const ERROR_BLINK_ROUNDS: u32 = 3;
+
+// What the display's bus runs from, see display::bus
+pub struct Clocks {
+    pub system: HertzU32,
+    pub peripheral: HertzU32,
+}
+
+// What set_up hands over to run
+pub struct Firmware {
+    watchdog: Watchdog,
+    last_reset: Option<Reset>,
+    // For logging how long flushes take, see peripherals.rs
+    cycles: chip::CycleCounter,
+    clocks: Clocks,
+    resets: pac::RESETS,
+    led: board::Led,
+    button1: Unconfigured<board::Button1>,
+    button2: Unconfigured<board::Button2>,
+    display_pins: board::DisplayPins,
+    #[cfg(feature = "i2c")]
+    i2c: pac::I2C0,
+    #[cfg(feature = "spi")]
+    spi: pac::SPI0,
+    // For core1, see flusher.rs
+    #[cfg(not(feature = "i2c-dma"))]
+    fifo: SioFifo,
+    #[cfg(not(feature = "i2c-dma"))]
+    psm: pac::PSM,
+    #[cfg(not(feature = "i2c-dma"))]
+    ppb: pac::PPB,
+    // See dma_flusher.rs
+    #[cfg(feature = "i2c-dma")]
+    dma: pac::DMA,
+    seed: SeedMixer,
+    battery: Option<Battery>,
+    rgb_led: Ws2812,
+    timer: chip::Timer,
+    usb_bus: UsbBusAllocator<UsbBus>,
+    link: chip::HostLink,
+}
+
+impl Firmware {
+    pub fn set_up(mut pac: pac::Peripherals, core: cortex_m::Peripherals) -> Self {
+        // First, before the clocks are set up, so a reset by the watchdog is noticed
+        let reset_by_watchdog = pac.WATCHDOG.reason().read().timer().bit_is_set();
+        let mut watchdog = Watchdog::new(pac.WATCHDOG);
+        let last_reset = recovery::start(&mut watchdog, reset_by_watchdog);
+        let sio = Sio::new(pac.SIO);
+
+        let clocks = init_clocks_and_plls(
+            board::XOSC_CRYSTAL_FREQ,
+            pac.XOSC,
+            pac.CLOCKS,
+            pac.PLL_SYS,
+            pac.PLL_USB,
+            &mut pac.RESETS,
+            &mut watchdog,
+        )
+        .ok()
+        .unwrap();
+
+        let pins = board::split(board::Pins::new(
+            pac.IO_BANK0,
+            pac.PADS_BANK0,
+            sio.gpio_bank0,
+            &mut pac.RESETS,
+        ));
+        let uart_pins = (pins.uart_tx.into_function(), pins.uart_rx.into_function());
+        let uart = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+            .enable(
+                UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+                clocks.peripheral_clock.freq(),
+            )
+            .unwrap();
+        let link = UartLink::new(uart);
+
+        let mut adc = Adc::new(pac.ADC, &mut pac.RESETS);
+        let mut adc_pin_0 = AdcPin::new(pins.seed_adc.into_floating_input()).unwrap();
+        // The floating pin and the temperature sensor only vary in their lowest bits, so take
+        // several samples of each, and the ring oscillator's random bit
+        let mut seed = SeedMixer::new();
+        for _ in 0..16 {
+            let sample: u16 = adc.read(&mut adc_pin_0).unwrap();
+            seed.add(sample as u64);
+        }
+        let mut temperature_sensor = adc.take_temp_sensor().unwrap();
+        for _ in 0..4 {
+            let sample: u16 = adc.read(&mut temperature_sensor).unwrap();
+            seed.add(sample as u64);
+        }
+        let rosc_bits = (0..64).fold(0, |bits, _| {
+            bits << 1 | pac.ROSC.randombit().read().randombit().bit() as u64
+        });
+        seed.add(rosc_bits);
+        // Different from one power on to the next even if everything above happens to repeat
+        #[cfg(feature = "rp2040")]
+        if let Some(previous_seed) = storage::read_seed() {
+            seed.add(previous_seed);
+        }
+        #[cfg(feature = "rp2350")]
+        for word in chip::read_trng(pac.TRNG, &mut pac.RESETS) {
+            seed.add(word as u64);
+        }
+        // Kept from before this boot, in case the battery running out is why it rebooted
+        let saved_battery = storage::read_battery();
+        if let Some(level) = saved_battery {
+            info!("Battery at the last save: {}%", level);
+        }
+        let battery = pins.vsys.map(|pin| {
+            let pin = AdcPin::new(pin.into_floating_input()).unwrap();
+            Battery::new(adc, pin, saved_battery)
+        });
+        let rgb_led = Ws2812::new(pins.rgb_led, pac.PIO0, &mut pac.RESETS);
+
+        // The RP2350 renamed these
+        #[cfg(feature = "rp2040")]
+        let (timer, usb, usb_dpram) = (pac.TIMER, pac.USBCTRL_REGS, pac.USBCTRL_DPRAM);
+        #[cfg(feature = "rp2350")]
+        let (timer, usb, usb_dpram) = (pac.TIMER0, pac.USB, pac.USB_DPRAM);
+        let timer = chip::timer(timer, &mut pac.RESETS, &clocks);
+        let usb_bus = UsbBusAllocator::new(UsbBus::new(
+            usb,
+            usb_dpram,
+            clocks.usb_clock,
+            true,
+            &mut pac.RESETS,
+        ));
+
+        Firmware {
+            watchdog,
+            last_reset,
+            cycles: chip::CycleCounter::new(core),
+            clocks: Clocks {
+                system: clocks.system_clock.freq(),
+                peripheral: clocks.peripheral_clock.freq(),
+            },
+            resets: pac.RESETS,
+            led: pins.led,
+            button1: pins.button1,
+            button2: pins.button2,
+            display_pins: pins.display,
+            #[cfg(feature = "i2c")]
+            i2c: pac.I2C0,
+            #[cfg(feature = "spi")]
+            spi: pac.SPI0,
+            #[cfg(not(feature = "i2c-dma"))]
+            fifo: sio.fifo,
+            #[cfg(not(feature = "i2c-dma"))]
+            psm: pac.PSM,
+            #[cfg(not(feature = "i2c-dma"))]
+            ppb: pac.PPB,
+            #[cfg(feature = "i2c-dma")]
+            dma: pac.DMA,
+            seed,
+            battery,
+            rgb_led,
+            timer,
+            usb_bus,
+            link,
+        }
+    }
+
+    pub fn run(self) -> ! {
+        let Firmware {
+            mut watchdog,
+            last_reset,
+            cycles,
+            clocks,
+            mut resets,
+            mut led,
+            button1,
+            button2,
+            display_pins,
+            #[cfg(feature = "i2c")]
+            i2c,
+            #[cfg(feature = "spi")]
+            spi,
+            #[cfg(not(feature = "i2c-dma"))]
+            mut fifo,
+            #[cfg(not(feature = "i2c-dma"))]
+            psm,
+            #[cfg(not(feature = "i2c-dma"))]
+            ppb,
+            #[cfg(feature = "i2c-dma")]
+            dma,
+            seed,
+            battery,
+            mut rgb_led,
+            timer,
+            usb_bus,
+            link,
+        } = self;
+        // SysTick counts cycles on the RP2040, so delays use a copy of the timer instead
+        let mut delay = timer;
+        watchdog.pause_on_debug(true);
+        watchdog.start(MicrosDurationU32::millis(WATCHDOG_TIMEOUT_MS));
+        let console = UsbConsole::new(&usb_bus, Settings::default());
+
+        let mut notice_buffer = [0u8; 64];
+        let notice = last_reset.and_then(|reset| {
+            // Without an error recorded, the watchdog ran out because the firmware hung or panicked
+            let error =
+                ErrorCode::from_code(reset.error_code).map_or("Hang or crash", ErrorCode::name);
+            let text = format_args!("Restarted after\n{}\nRestarts: {}", error, reset.count);
+            format_no_std::show(&mut notice_buffer, text).ok()
+        });
+
+        let result = (|| -> Result<(), Error> {
+            #[cfg(feature = "i2c")]
+            let bus = display::bus(display_pins, i2c, &mut resets, &clocks);
+            #[cfg(feature = "spi")]
+            let bus = display::bus(display_pins, spi, &mut resets, &clocks);
+            let display = display::new(bus, &mut delay)?;
+            let frame = Framebuffer::new(display.bounding_box().size)?;
+            #[cfg(not(feature = "i2c-dma"))]
+            let flusher = {
+                chip::spawn_core1(psm, ppb, &mut fifo, move || flusher::run(display));
+                flusher::Flusher::new(fifo)
+            };
+            #[cfg(feature = "i2c-dma")]
+            let flusher = dma_flusher::Flusher::new(display, dma.split(&mut resets).ch0);
+
+            let device = Device {
+                display_storage: frame,
+                button1_pin: button1.into_pull_up_input(),
+                button2_pin: button2.into_pull_up_input(),
+                led_pin: &mut led,
+                seed,
+                hardware: Peripherals {
+                    flusher,
+                    flush_times: Default::default(),
+                    cycles,
+                    watchdog: &mut watchdog,
+                    battery,
+                    rgb_led: &mut rgb_led,
+                    timer,
+                    console,
+                    link,
+                },
+            };
+            let mut game = program::game::Game::new(device)?;
+            if let Some(notice) = notice {
+                game.set_notice(notice);
+            }
+            let result = game.run_game();
+            if let Err(error) = &result {
+                // Fails too if the display is what went wrong, the LEDs still show the code then
+                let _ = game.show_error(error.into());
+            }
+            result
+        })();
+        // The game only returns on errors
+        if let Err(error) = result {
+            info!("Error: {}", error);
+            let error = ErrorCode::from(&error);
+            recovery::record_error(&mut watchdog, error.code());
+            for _ in 0..ERROR_BLINK_ROUNDS {
+                for (on, ms) in error.blink_pattern() {
+                    // The RGB LED too, on the Pro Micro it is the only one
+                    if on {
+                        led.set_high().unwrap();
+                        rgb_led.set(Rgb888::RED);
+                    } else {
+                        led.set_low().unwrap();
+                        rgb_led.set(Rgb888::BLACK);
+                    }
+                    delay.delay_ms(ms);
+                    watchdog.feed();
+                }
+            }
+        }
+        // The watchdog, no longer fed, resets the Pico
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
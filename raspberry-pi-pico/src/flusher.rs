@@ -0,0 +1,150 @@
+// Sends frames to the display from core1, so core0 doesn't wait for the I2C or SPI transfer and
+// keeps running the game and reading the buttons. Core0 copies each finished frame into one of two
+// slots, along with the area the game changed, and sends its number through the SIO FIFO. Core1
+// copies it out, sends the number back so the slot can be reused, and transfers the area while
+// core0 draws the next frame.
+use crate::display;
+use crate::error::Error;
+use crate::hal::pac;
+use crate::hal::sio::{Sio, SioFifo};
+use crate::screen;
+use core::cell::RefCell;
+use critical_section::Mutex;
+use embedded_graphics::primitives::Rectangle;
+use program::framebuffer::Framebuffer;
+
+const SLOTS: usize = 2;
+// Set in a slot number core1 sends back when transferring that frame failed
+const FAILED: u32 = 1 << 8;
+// Sent to core1 to have it wait in RAM while core0 writes the flash, and sent back once it does
+const PAUSE: u32 = 1 << 9;
+// The SIO registers park uses, the same on the RP2040 and the RP2350
+const SIO_BASE: u32 = 0xd000_0000;
+
+// A frame and the area of it the game changed
+type Slot = Option<(Framebuffer, Rectangle)>;
+
+static FRAMES: Mutex<RefCell<[Slot; SLOTS]>> = Mutex::new(RefCell::new([None, None]));
+static ERROR: Mutex<RefCell<Option<Error>>> = Mutex::new(RefCell::new(None));
+
+// Core0's side
+pub struct Flusher {
+    fifo: SioFifo,
+    free: [bool; SLOTS],
+    failed: bool,
+}
+
+impl Flusher {
+    pub fn new(fifo: SioFifo) -> Self {
+        Flusher {
+            fifo,
+            free: [true; SLOTS],
+            failed: false,
+        }
+    }
+
+    // Only waits when core1 is still busy with both earlier frames. Returns the error of a transfer
+    // that failed since the last wait.
+    pub fn wait(&mut self) -> Result<(), Error> {
+        while let Some(message) = self.fifo.read() {
+            self.receive(message);
+        }
+        while !self.free.contains(&true) {
+            let message = self.fifo.read_blocking();
+            self.receive(message);
+        }
+        self.take_error()
+    }
+
+    // After wait, which leaves a slot free
+    pub fn send(&mut self, frame: &Framebuffer, area: Rectangle) {
+        let slot = self.free.iter().position(|&free| free).unwrap();
+        self.free[slot] = false;
+        critical_section::with(|cs| FRAMES.borrow_ref_mut(cs)[slot] = Some((frame.clone(), area)));
+        self.fifo.write_blocking(slot as u32);
+    }
+
+    // Core1 runs from flash too, so it waits in RAM until the write is done
+    pub fn writing_flash(&mut self, write: impl FnOnce()) {
+        self.fifo.write_blocking(PAUSE);
+        loop {
+            match self.fifo.read_blocking() {
+                PAUSE => break,
+                message => self.receive(message),
+            }
+        }
+        write();
+        self.fifo.write_blocking(PAUSE);
+    }
+
+    fn receive(&mut self, message: u32) {
+        self.free[(message & !FAILED) as usize] = true;
+        self.failed |= message & FAILED != 0;
+    }
+
+    fn take_error(&mut self) -> Result<(), Error> {
+        if core::mem::take(&mut self.failed) {
+            if let Some(error) = critical_section::with(|cs| ERROR.borrow_ref_mut(cs).take()) {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Core1's side, which owns the display. Only draws the area the game changed, so the SSD1306
+// driver still sends just that.
+pub fn run(mut display: display::Display) -> ! {
+    let pac = unsafe { pac::Peripherals::steal() };
+    let mut fifo = Sio::new(pac.SIO).fifo;
+    loop {
+        let slot = fifo.read_blocking();
+        if slot == PAUSE {
+            park();
+            continue;
+        }
+        let frame = critical_section::with(|cs| FRAMES.borrow_ref_mut(cs)[slot as usize].take());
+        let Some((frame, area)) = frame else {
+            continue;
+        };
+        match show(&mut display, &frame, area) {
+            Ok(()) => fifo.write_blocking(slot),
+            Err(error) => {
+                critical_section::with(|cs| *ERROR.borrow_ref_mut(cs) = Some(error));
+                fifo.write_blocking(slot | FAILED);
+            }
+        }
+    }
+}
+
+// Tells core0 core1 is paused and waits for the next message, without touching the flash. In asm,
+// as unoptimized builds would call into the flash for the register accesses.
+#[link_section = ".data.ram_func"]
+#[inline(never)]
+fn park() {
+    unsafe {
+        core::arch::asm!(
+            // Wait for room in the FIFO to core0 (FIFO_ST.RDY), and send PAUSE to FIFO_WR
+            "1:",
+            "ldr {status}, [{sio}, #0x50]",
+            "lsrs {status}, {status}, #2",
+            "bcc 1b",
+            "str {pause}, [{sio}, #0x54]",
+            // Wait for a message from core0 (FIFO_ST.VLD), and drop it from FIFO_RD
+            "2:",
+            "ldr {status}, [{sio}, #0x50]",
+            "lsrs {status}, {status}, #1",
+            "bcc 2b",
+            "ldr {status}, [{sio}, #0x58]",
+            sio = in(reg) SIO_BASE,
+            pause = in(reg) PAUSE,
+            status = out(reg) _,
+        );
+    }
+}
+
+fn show(display: &mut display::Display, frame: &Framebuffer, area: Rectangle) -> Result<(), Error> {
+    screen::draw_area(display, frame, area)?;
+    display.flush()?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+// The parts of the firmware that don't depend on the chip, so they build and are tested on the host.
+// On Arm, the rest of the firmware too, for the chip the board feature picks, which the RP2040 and
+// RP2350 binaries set up and run.
+#![no_std]
+
+pub mod device;
+pub mod screen;
+
+#[cfg(all(target_arch = "arm", feature = "rp2040"))]
+pub use rp2040_hal as hal;
+#[cfg(all(target_arch = "arm", feature = "rp2350"))]
+pub use rp235x_hal as hal;
+
+#[cfg(target_arch = "arm")]
+pub mod battery;
+#[cfg(target_arch = "arm")]
+pub mod board;
+#[cfg(target_arch = "arm")]
+pub mod chip;
+#[cfg(target_arch = "arm")]
+pub mod display;
+#[cfg(all(target_arch = "arm", feature = "i2c-dma"))]
+pub mod dma_flusher;
+#[cfg(target_arch = "arm")]
+pub mod error;
+#[cfg(target_arch = "arm")]
+pub mod firmware;
+#[cfg(all(target_arch = "arm", not(feature = "i2c-dma")))]
+pub mod flusher;
+#[cfg(target_arch = "arm")]
+pub mod peripherals;
+#[cfg(target_arch = "arm")]
+pub mod recovery;
+#[cfg(target_arch = "arm")]
+pub mod storage;
+#[cfg(target_arch = "arm")]
+pub mod uart_link;
+#[cfg(target_arch = "arm")]
+pub mod usb_console;
+#[cfg(target_arch = "arm")]
+pub mod ws2812;
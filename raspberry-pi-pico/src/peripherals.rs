@@ -0,0 +1,121 @@
+// The chip's side of device::Device, for both the RP2040 and the RP2350
+use crate::battery::Battery;
+use crate::chip;
+use crate::device::Hardware;
+#[cfg(feature = "i2c-dma")]
+use crate::dma_flusher::Flusher;
+use crate::error::Error;
+#[cfg(not(feature = "i2c-dma"))]
+use crate::flusher::Flusher;
+use crate::hal::watchdog::Watchdog;
+use crate::recovery;
+use crate::storage;
+use crate::usb_console::UsbConsole;
+use crate::ws2812::Ws2812;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::primitives::Rectangle;
+use embedded_hal::delay::DelayNs;
+use program::abstract_device::{GameEvent, Inputs};
+use program::console::Request;
+use program::framebuffer::Framebuffer;
+use program::settings::Settings;
+
+// How many frames flush_display's mean cost is logged for
+const FLUSH_LOG_FRAMES: u32 = 256;
+
+// The cycles core0 spends handing frames over, to compare the ways of flushing. Waiting for an
+// earlier frame's transfer isn't counted, that depends on the bus rather than the flusher.
+#[derive(Default)]
+pub struct FlushTimes {
+    frames: u32,
+    cycles: u64,
+}
+
+impl FlushTimes {
+    fn add(&mut self, cycles: u32) {
+        self.frames += 1;
+        self.cycles += cycles as u64;
+        if self.frames == FLUSH_LOG_FRAMES {
+            let cycles = self.cycles / self.frames as u64;
+            defmt::debug!("flush_display: {} cycles per frame", cycles);
+            *self = FlushTimes::default();
+        }
+    }
+}
+
+pub struct Peripherals<'a> {
+    pub flusher: Flusher,
+    pub flush_times: FlushTimes,
+    pub cycles: chip::CycleCounter,
+    pub watchdog: &'a mut Watchdog,
+    pub battery: Option<Battery>,
+    pub rgb_led: &'a mut Ws2812,
+    pub timer: chip::Timer,
+    pub console: UsbConsole<'a>,
+    pub link: chip::HostLink,
+}
+
+impl<'a> Hardware for Peripherals<'a> {
+    type Error = Error;
+    fn poll(&mut self) {
+        self.watchdog.feed();
+        self.link.poll();
+        if let Some(Request::Bootloader) = self.console.poll() {
+            // Give the reply a moment to reach the host before the USB device goes away
+            for _ in 0..100 {
+                self.timer.delay_ms(1);
+                self.console.poll();
+            }
+            recovery::stop(self.watchdog);
+            chip::reset_to_bootloader();
+        }
+    }
+    fn remote_inputs(&self) -> Inputs {
+        self.link.inputs()
+    }
+    fn delay_ms(&mut self, ms: u32) {
+        self.timer.delay_ms(ms);
+    }
+    fn ticks(&mut self) -> u64 {
+        self.timer.get_counter().ticks()
+    }
+    fn flush(&mut self, frame: &Framebuffer, area: Rectangle) -> Result<(), Self::Error> {
+        // Core1 or the DMA sends the frame to the display while the game goes on, see flusher.rs
+        // and dma_flusher.rs
+        self.flusher.wait()?;
+        let start = self.cycles.now();
+        self.flusher.send(frame, area);
+        self.flush_times.add(self.cycles.since(start));
+        Ok(())
+    }
+    #[cfg(feature = "rp2040")]
+    fn store_seed(&mut self, seed: u64) {
+        self.watchdog.feed();
+        self.flusher.writing_flash(|| storage::write_seed(seed));
+    }
+    // The RP2350 has a hardware RNG instead, see chip.rs, so core1 isn't paused for nothing
+    #[cfg(feature = "rp2350")]
+    fn store_seed(&mut self, _seed: u64) {}
+    fn battery_level(&mut self) -> Option<u8> {
+        let battery = self.battery.as_mut()?;
+        let level = battery.level();
+        if battery.should_save(level) {
+            self.watchdog.feed();
+            self.flusher.writing_flash(|| storage::write_battery(level));
+        }
+        Some(level)
+    }
+    fn set_rgb_led(&mut self, color: Rgb888) {
+        self.rgb_led.set(color);
+    }
+    fn on_event(&mut self, event: GameEvent) {
+        self.console.on_event(event);
+        self.link.on_event(event);
+    }
+    fn take_settings(&mut self) -> Option<Settings> {
+        // One at a time, so settings from both are applied in turn rather than lost
+        self.console
+            .take_settings()
+            .or_else(|| self.link.take_settings())
+    }
+}
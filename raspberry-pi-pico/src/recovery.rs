@@ -0,0 +1,44 @@
+use crate::hal::watchdog::{ScratchRegister, Watchdog};
+
+// Scratch registers 4 to 7 are used by the boot ROM, these two survive resets by the watchdog but
+// not a power cycle or the reset pin
+const STATE: ScratchRegister = ScratchRegister::Scratch0;
+const RESETS: ScratchRegister = ScratchRegister::Scratch1;
+// The high bits of STATE while the firmware runs, the low byte is the error code
+const RUNNING: u32 = 0x5649_4d00;
+
+pub struct Reset {
+    pub error_code: u8,
+    // How often the Pico has reset itself since it was powered on
+    pub count: u32,
+}
+
+// What made the watchdog reset the Pico, if it did. Marks the firmware as running from here on,
+// so a hang or crash that stops the watchdog being fed shows up on the next boot too.
+pub fn start(watchdog: &mut Watchdog, reset_by_watchdog: bool) -> Option<Reset> {
+    let state = watchdog.read_scratch(STATE);
+    let reset = if reset_by_watchdog && state & !0xff == RUNNING {
+        let count = watchdog.read_scratch(RESETS).wrapping_add(1);
+        watchdog.write_scratch(RESETS, count);
+        Some(Reset {
+            error_code: state as u8,
+            count,
+        })
+    } else {
+        watchdog.write_scratch(RESETS, 0);
+        None
+    };
+    watchdog.write_scratch(STATE, RUNNING);
+    reset
+}
+
+// Called before the watchdog is left to reset the Pico
+pub fn record_error(watchdog: &mut Watchdog, code: u8) {
+    watchdog.write_scratch(STATE, RUNNING | code as u32);
+}
+
+// For resets that are meant to happen, like into the bootloader, which also uses the watchdog
+pub fn stop(watchdog: &mut Watchdog) {
+    watchdog.write_scratch(STATE, 0);
+    watchdog.write_scratch(RESETS, 0);
+}
@@ -0,0 +1,133 @@
+// Getting frames onto the display, for whichever bus and core sends them
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PointsIter, Rectangle};
+use program::framebuffer::Framebuffer;
+
+#[cfg(all(feature = "ssd1306", feature = "i2c"))]
+pub type Ssd1306I2c<I2C, Size> = ssd1306::Ssd1306<
+    ssd1306::prelude::I2CInterface<I2C>,
+    Size,
+    ssd1306::mode::BufferedGraphicsMode<Size>,
+>;
+
+// An SSD1306 at its usual address, not initialized yet
+#[cfg(all(feature = "ssd1306", feature = "i2c"))]
+pub fn ssd1306_i2c<I2C: embedded_hal::i2c::I2c, Size: ssd1306::size::DisplaySize>(
+    i2c: I2C,
+    size: Size,
+) -> Ssd1306I2c<I2C, Size> {
+    let interface = ssd1306::I2CDisplayInterface::new(i2c);
+    ssd1306::Ssd1306::new(interface, size, ssd1306::prelude::DisplayRotation::Rotate0)
+        .into_buffered_graphics_mode()
+}
+
+// Draws the pixels of `frame` in `area`, the one the game says changed. The SSD1306 driver then
+// only sends that area.
+pub fn draw_area<D: DrawTarget<Color = BinaryColor>>(
+    display: &mut D,
+    frame: &Framebuffer,
+    area: Rectangle,
+) -> Result<(), D::Error> {
+    let pixels = area
+        .points()
+        .map(|point| Pixel(point, BinaryColor::from(frame.get_pixel(point))));
+    display.draw_iter(pixels)
+}
+
+#[cfg(all(test, feature = "ssd1306", feature = "i2c"))]
+mod tests {
+    extern crate std;
+    use crate::screen::{draw_area, ssd1306_i2c};
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use program::framebuffer::Framebuffer;
+    use ssd1306::mode::DisplayConfig;
+    use ssd1306::size::DisplaySize128x32;
+    use std::vec;
+    use std::vec::Vec;
+
+    const ADDRESS: u8 = 0x3c;
+
+    fn command(bytes: &[u8]) -> Transaction {
+        let mut data = vec![0x00];
+        data.extend_from_slice(bytes);
+        Transaction::write(ADDRESS, data)
+    }
+
+    fn init() -> Vec<Transaction> {
+        vec![
+            command(&[0xae]),
+            command(&[0xd5, 0x80]),
+            command(&[0xa8, 0x1f]),
+            command(&[0xd3, 0x00]),
+            command(&[0x40]),
+            command(&[0x8d, 0x14]),
+            command(&[0x20, 0x00]),
+            command(&[0xda, 0x02]),
+            command(&[0xa1]),
+            command(&[0xc8]),
+            command(&[0xd9, 0x21]),
+            command(&[0x81, 0x5f]),
+            command(&[0xdb, 0x40]),
+            command(&[0xa4]),
+            command(&[0xa6]),
+            command(&[0x2e]),
+            command(&[0xaf]),
+        ]
+    }
+
+    // Columns 0 to 127 and pages 0 to 3, then the blank pixels 16 bytes per write
+    fn whole_frame() -> Vec<Transaction> {
+        let mut transactions = vec![command(&[0x21, 0, 127]), command(&[0x22, 0, 3])];
+        let mut data = vec![0x40];
+        data.extend_from_slice(&[0; 16]);
+        transactions.extend((0..32).map(|_| Transaction::write(ADDRESS, data.clone())));
+        transactions
+    }
+
+    #[test]
+    fn test_init() {
+        let mut i2c = Mock::new(&init());
+        let mut display = ssd1306_i2c(i2c.clone(), DisplaySize128x32);
+        display.init().unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_first_flush_sends_whole_frame() {
+        let expectations: Vec<Transaction> = init().into_iter().chain(whole_frame()).collect();
+        let mut i2c = Mock::new(&expectations);
+        let mut display = ssd1306_i2c(i2c.clone(), DisplaySize128x32);
+        display.init().unwrap();
+        display.flush().unwrap();
+        i2c.done();
+    }
+
+    #[test]
+    fn test_flush_sends_changed_area() {
+        let mut frame = Framebuffer::new(Size::new(128, 32)).unwrap();
+        frame.set_pixel(Point::new(10, 9), true);
+        // Column 10 of page 1, where row 9 is bit 1
+        let changed = [
+            command(&[0x21, 10, 10]),
+            command(&[0x22, 1, 1]),
+            Transaction::write(ADDRESS, vec![0x40, 0x02]),
+        ];
+        let expectations: Vec<Transaction> = init()
+            .into_iter()
+            .chain(whole_frame())
+            .chain(changed)
+            .collect();
+        let mut i2c = Mock::new(&expectations);
+        let mut display = ssd1306_i2c(i2c.clone(), DisplaySize128x32);
+        display.init().unwrap();
+        display.flush().unwrap();
+
+        let area = Rectangle::new(Point::new(10, 9), Size::new(1, 1));
+        draw_area(&mut display, &frame, area).unwrap();
+        display.flush().unwrap();
+        i2c.done();
+    }
+}
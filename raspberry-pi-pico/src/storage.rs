@@ -0,0 +1,100 @@
+// Data that survives a power cycle, in the last sector of the flash, which memory.x keeps out of
+// FLASH. The RP2040 has rp2040-flash for writing it, the RP2350 the boot ROM's functions.
+#[cfg(feature = "rp2350")]
+use crate::hal::{pac, rom_data};
+#[cfg(feature = "rp2040")]
+use rp2040_flash::flash;
+
+const SECTOR_SIZE: u32 = 4096;
+// The Pico has 2 MiB of flash, the Pico 2 4 MiB
+#[cfg(feature = "rp2040")]
+const FLASH_SIZE: u32 = 2048 * 1024;
+#[cfg(feature = "rp2350")]
+const FLASH_SIZE: u32 = 4096 * 1024;
+const SECTOR_OFFSET: u32 = FLASH_SIZE - SECTOR_SIZE;
+const XIP_BASE: u32 = 0x1000_0000;
+// Erased flash reads as all ones, so a Pico that never saved anything has no seed. The RP2350 has a
+// hardware RNG instead, see chip.rs, so only the RP2040 saves one.
+#[cfg(feature = "rp2040")]
+const MAGIC: u32 = u32::from_le_bytes(*b"SEED");
+// The last battery level saved, after the seed, for finding out why a Pico ran out of battery
+const BATTERY_OFFSET: u32 = 12;
+const BATTERY_MAGIC: u32 = u32::from_le_bytes(*b"BATT");
+// Everything saved, the rest of the sector stays erased
+const SAVED_LENGTH: usize = BATTERY_OFFSET as usize + 8;
+
+fn read_u32(offset: u32) -> u32 {
+    let address = (XIP_BASE + SECTOR_OFFSET + offset) as *const u32;
+    // The sector is mapped into memory like the rest of the flash
+    unsafe { core::ptr::read_volatile(address) }
+}
+
+#[cfg(feature = "rp2040")]
+pub fn read_seed() -> Option<u64> {
+    if read_u32(0) != MAGIC {
+        return None;
+    }
+    Some(read_u32(4) as u64 | (read_u32(8) as u64) << 32)
+}
+
+pub fn read_battery() -> Option<u8> {
+    if read_u32(BATTERY_OFFSET) != BATTERY_MAGIC {
+        return None;
+    }
+    Some(read_u32(BATTERY_OFFSET + 4) as u8)
+}
+
+#[cfg(feature = "rp2040")]
+pub fn write_seed(seed: u64) {
+    let mut bytes = [0; 12];
+    bytes[..4].copy_from_slice(&MAGIC.to_le_bytes());
+    bytes[4..].copy_from_slice(&seed.to_le_bytes());
+    write(0, &bytes);
+}
+
+pub fn write_battery(level: u8) {
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&BATTERY_MAGIC.to_le_bytes());
+    bytes[4..].copy_from_slice(&(level as u32).to_le_bytes());
+    write(BATTERY_OFFSET, &bytes);
+}
+
+// Writes `bytes` at `offset` into the sector's first page, keeping what else is saved there
+fn write(offset: u32, bytes: &[u8]) {
+    let mut page = [0xff; 256];
+    for (index, word) in page[..SAVED_LENGTH].chunks_exact_mut(4).enumerate() {
+        word.copy_from_slice(&read_u32(index as u32 * 4).to_le_bytes());
+    }
+    page[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+    // Nothing may run from flash while it is written, which an interrupt handler could. When
+    // core1 sends the frames, it waits in RAM meanwhile, see Flusher::writing_flash.
+    cortex_m::interrupt::free(|_| unsafe { erase_and_program(&page) });
+}
+
+#[cfg(feature = "rp2040")]
+unsafe fn erase_and_program(page: &[u8; 256]) {
+    flash::flash_range_erase(SECTOR_OFFSET, SECTOR_SIZE, true);
+    flash::flash_range_program(SECTOR_OFFSET, page, true);
+}
+
+// The RP2350's ROM functions leave the flash readable, if slowly, when they return, so unlike on
+// the RP2040 this can run from flash. Only the ROM runs while the flash is busy.
+#[cfg(feature = "rp2350")]
+unsafe fn erase_and_program(page: &[u8; 256]) {
+    // 64 KiB blocks erase faster, the ROM uses them where the range allows
+    const BLOCK_SIZE: u32 = 1 << 16;
+    const BLOCK_ERASE: u8 = 0xd8;
+    // The read mode the boot ROM picked, which flash_exit_xip replaces with its slowest one
+    let qmi = &*pac::QMI::ptr();
+    let timing = qmi.m0_timing().read().bits();
+    let rfmt = qmi.m0_rfmt().read().bits();
+    let rcmd = qmi.m0_rcmd().read().bits();
+    rom_data::connect_internal_flash();
+    rom_data::flash_exit_xip();
+    rom_data::flash_range_erase(SECTOR_OFFSET, SECTOR_SIZE as usize, BLOCK_SIZE, BLOCK_ERASE);
+    rom_data::flash_range_program(SECTOR_OFFSET, page.as_ptr(), page.len());
+    rom_data::flash_flush_cache();
+    qmi.m0_timing().write(|w| w.bits(timing));
+    qmi.m0_rfmt().write(|w| w.bits(rfmt));
+    qmi.m0_rcmd().write(|w| w.bits(rcmd));
+}
@@ -0,0 +1,61 @@
+use program::abstract_device::{GameEvent, Inputs};
+use program::settings::Settings;
+use protocol::{Decoder, DeviceMessage, HostMessage, MAX_FRAME};
+use crate::hal::uart::{Enabled, UartDevice, UartPeripheral, ValidUartPinout};
+
+// The host control protocol from the protocol crate on a UART, for a desktop tool or another
+// microcontroller. Polled like the USB console, so it only ever reads what has already arrived.
+pub struct UartLink<D: UartDevice, P: ValidUartPinout<D>> {
+    uart: UartPeripheral<Enabled, D, P>,
+    decoder: Decoder,
+    // Buttons held by the host
+    inputs: Inputs,
+    new_settings: Option<Settings>,
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> UartLink<D, P> {
+    pub fn new(uart: UartPeripheral<Enabled, D, P>) -> Self {
+        UartLink {
+            uart,
+            decoder: Decoder::new(),
+            inputs: Inputs::default(),
+            new_settings: None,
+        }
+    }
+
+    pub fn poll(&mut self) {
+        let mut buffer = [0; 32];
+        // Nothing to read, or a break or overrun, which at worst costs a frame the CRC catches
+        let Ok(count) = self.uart.read_raw(&mut buffer) else {
+            return;
+        };
+        for &byte in &buffer[..count] {
+            match self.decoder.push(byte) {
+                Some(Ok(HostMessage::Ping)) => self.send(DeviceMessage::Pong),
+                Some(Ok(HostMessage::Inputs(inputs))) => self.inputs = inputs,
+                Some(Ok(HostMessage::SetSettings(settings))) => self.new_settings = Some(settings),
+                Some(Err(_)) | None => {}
+            }
+        }
+    }
+
+    // Frames are shorter than the UART's FIFO, so this only waits when the host can't keep up
+    fn send(&mut self, message: DeviceMessage) {
+        let mut frame = [0; MAX_FRAME];
+        if let Ok(frame) = protocol::encode(&message, &mut frame) {
+            self.uart.write_full_blocking(frame);
+        }
+    }
+
+    pub fn inputs(&self) -> Inputs {
+        self.inputs
+    }
+
+    pub fn on_event(&mut self, event: GameEvent) {
+        self.send(event.into());
+    }
+
+    pub fn take_settings(&mut self) -> Option<Settings> {
+        self.new_settings.take()
+    }
+}
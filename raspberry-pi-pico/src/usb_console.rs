@@ -0,0 +1,98 @@
+use core::fmt;
+use program::abstract_device::GameEvent;
+use program::console::{Console, Request};
+use program::settings::Settings;
+use crate::hal::usb::UsbBus;
+use usb_device::bus::UsbBusAllocator;
+use usb_device::device::{StringDescriptors, UsbDevice, UsbDeviceBuilder, UsbVidPid};
+use usb_device::UsbError;
+use usbd_serial::SerialPort;
+
+// The shared VID/PID for CDC-ACM devices from pid.codes
+const VID_PID: UsbVidPid = UsbVidPid(0x16c0, 0x27dd);
+// How often a write polls for the host to make room before the rest of it is dropped
+const MAX_WRITE_POLLS: u32 = 1000;
+
+struct Port<'a> {
+    device: UsbDevice<'a, UsbBus>,
+    serial: SerialPort<'a, UsbBus>,
+}
+
+impl Port<'_> {
+    fn poll(&mut self) -> bool {
+        self.device.poll(&mut [&mut self.serial])
+    }
+}
+
+impl fmt::Write for Port<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Nothing reads the output without a terminal open, so it's dropped instead of stalling
+        // the game
+        if !self.serial.dtr() {
+            return Ok(());
+        }
+        let mut bytes = s.as_bytes();
+        let mut polls = 0;
+        while !bytes.is_empty() && polls < MAX_WRITE_POLLS {
+            match self.serial.write(bytes) {
+                Ok(written) => bytes = &bytes[written..],
+                Err(UsbError::WouldBlock) => {
+                    polls += 1;
+                    self.poll();
+                }
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+// The text console from program on a USB CDC-ACM serial port, which hosts have drivers for
+pub struct UsbConsole<'a> {
+    port: Port<'a>,
+    console: Console,
+}
+
+impl<'a> UsbConsole<'a> {
+    pub fn new(bus: &'a UsbBusAllocator<UsbBus>, settings: Settings) -> Self {
+        let serial = SerialPort::new(bus);
+        let strings = StringDescriptors::default()
+            .manufacturer("visual-memory")
+            .product("Visual memory")
+            .serial_number("1");
+        let device = UsbDeviceBuilder::new(bus, VID_PID)
+            .strings(&[strings])
+            .unwrap()
+            .device_class(usbd_serial::USB_CLASS_CDC)
+            .build();
+        UsbConsole {
+            port: Port { device, serial },
+            console: Console::new(settings),
+        }
+    }
+
+    // Has to run every few ms, or the host gives up on the device
+    pub fn poll(&mut self) -> Option<Request> {
+        if !self.port.poll() {
+            return None;
+        }
+        let mut buffer = [0; 64];
+        let count = self.port.serial.read(&mut buffer).unwrap_or(0);
+        let mut request = None;
+        for &byte in &buffer[..count] {
+            // Writing to the port never fails, output that can't be sent is dropped
+            if let Ok(Some(new_request)) = self.console.receive(byte, &mut self.port) {
+                request = Some(new_request);
+            }
+        }
+        request
+    }
+
+    pub fn on_event(&mut self, event: GameEvent) {
+        let _ = self.console.on_event(event, &mut self.port);
+    }
+
+    pub fn take_settings(&mut self) -> Option<Settings> {
+        self.console.take_settings()
+    }
+}
@@ -0,0 +1,93 @@
+// WS2812 RGB LEDs (NeoPixels) on one data pin, sent by a PIO state machine. Every LED of a strip
+// gets the same colour; a single LED passes the extra ones on to nothing.
+use crate::board;
+use crate::chip;
+use crate::hal;
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use hal::gpio::{FunctionPio0, Pin, PullDown};
+use hal::pac;
+use hal::pio::{Buffers, PIOBuilder, PIOExt, Running, ShiftDirection, StateMachine, Tx, SM0};
+
+// LEDs on the strip, at most the TX FIFO's 8 words so a colour fits in it at once
+const LEDS: usize = 8;
+// Of 255, full brightness is blinding up close and draws 60 mA per LED
+const BRIGHTNESS: u32 = 32;
+// Cycles of each bit: high for T1, then high or low for T2 for a 1 or a 0, then low for T3
+const T1: u8 = 2;
+const T2: u8 = 5;
+const T3: u8 = 3;
+// 800 kHz bits
+const PIO_CLOCK_MHZ: u64 = 8;
+// The last word still being sent once the FIFO is empty, then the 50 µs low that latches the colours
+const LATCH_US: u64 = 30 + 60;
+
+pub type DataPin = Pin<board::RgbLed, FunctionPio0, PullDown>;
+
+pub struct Ws2812 {
+    tx: Tx<(pac::PIO0, SM0)>,
+    _state_machine: StateMachine<(pac::PIO0, SM0), Running>,
+    _pin: DataPin,
+}
+
+impl Ws2812 {
+    pub fn new(
+        pin: board::Unconfigured<board::RgbLed>,
+        pio: pac::PIO0,
+        resets: &mut pac::RESETS,
+    ) -> Self {
+        let pin: DataPin = pin.reconfigure();
+        // .side_set 1
+        // .wrap_target
+        //     out x, 1        side 0 [T3 - 1]
+        //     jmp !x do_zero  side 1 [T1 - 1]
+        //     jmp wrap_target side 1 [T2 - 1]
+        // do_zero:
+        //     nop             side 0 [T2 - 1]
+        // .wrap
+        let mut asm = pio::Assembler::new_with_side_set(pio::SideSet::new(false, 1, false));
+        let mut wrap_target = asm.label();
+        let mut wrap_source = asm.label();
+        let mut do_zero = asm.label();
+        asm.bind(&mut wrap_target);
+        asm.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);
+        asm.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1);
+        asm.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1);
+        asm.bind(&mut do_zero);
+        asm.nop_with_delay_and_side_set(T2 - 1, 0);
+        asm.bind(&mut wrap_source);
+        let program = asm.assemble_with_wrap(wrap_source, wrap_target);
+
+        let (mut pio, state_machine, _, _, _) = pio.split(resets);
+        let installed = pio.install(&program).unwrap();
+        // The divisor in 16.8 fixed point
+        let divisor = chip::SYSTEM_CLOCK_MHZ * 256 / PIO_CLOCK_MHZ;
+        let (mut state_machine, _, tx) = PIOBuilder::from_installed_program(installed)
+            .side_set_pin_base(pin.id().num)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(24)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point((divisor >> 8) as u16, divisor as u8)
+            .build(state_machine);
+        state_machine.set_pindirs([(pin.id().num, hal::pio::PinDir::Output)]);
+        let mut ws2812 = Ws2812 {
+            tx,
+            _state_machine: state_machine.start(),
+            _pin: pin,
+        };
+        ws2812.set(Rgb888::BLACK);
+        ws2812
+    }
+
+    pub fn set(&mut self, color: Rgb888) {
+        // Without the latch the new colour would be shifted on past the end of the strip
+        while !self.tx.is_empty() {}
+        cortex_m::asm::delay((LATCH_US * chip::SYSTEM_CLOCK_MHZ) as u32);
+        let scale = |channel: u8| channel as u32 * BRIGHTNESS / 255;
+        // Green, red and blue, most significant bit first, in the top 24 bits
+        let word = scale(color.g()) << 24 | scale(color.r()) << 16 | scale(color.b()) << 8;
+        for _ in 0..LEDS {
+            while !self.tx.write(word) {}
+        }
+    }
+}
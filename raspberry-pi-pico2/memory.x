@@ -0,0 +1,53 @@
+MEMORY {
+    /* The Pico 2 has 4 MiB of flash. The RP2350 boots from it directly, there is no boot2. The
+     * last 4K sector is left for data that survives a power cycle, see storage.rs */
+    FLASH : ORIGIN = 0x10000000, LENGTH = 4096K - 4K
+    /* SRAM0 to SRAM7, striped */
+    RAM   : ORIGIN = 0x20000000, LENGTH = 512K
+    /* SRAM8 and SRAM9, each mapped directly */
+    SRAM4 : ORIGIN = 0x20080000, LENGTH = 4K
+    SRAM5 : ORIGIN = 0x20081000, LENGTH = 4K
+}
+
+SECTIONS {
+    /* ### Boot ROM info
+     *
+     * Goes after .vector_table, to keep it in the first 4K of flash where the boot ROM (and
+     * picotool) look for the image definition
+     */
+    .start_block : ALIGN(4)
+    {
+        __start_block_addr = .;
+        KEEP(*(.start_block));
+        KEEP(*(.boot_info));
+    } > FLASH
+} INSERT AFTER .vector_table;
+
+/* Move .text to start after the image definition */
+_stext = ADDR(.start_block) + SIZEOF(.start_block);
+
+SECTIONS {
+    /* ### Picotool binary info entries */
+    .bi_entries : ALIGN(4)
+    {
+        __bi_entries_start = .;
+        KEEP(*(.bi_entries));
+        . = ALIGN(4);
+        __bi_entries_end = .;
+    } > FLASH
+} INSERT AFTER .text;
+
+SECTIONS {
+    /* ### Boot ROM extra info
+     *
+     * Goes after everything else in flash, so it could hold a signature
+     */
+    .end_block : ALIGN(4)
+    {
+        __end_block_addr = .;
+        KEEP(*(.end_block));
+    } > FLASH
+} INSERT AFTER .uninit;
+
+PROVIDE(start_to_end = __end_block_addr - __start_block_addr);
+PROVIDE(end_to_start = __start_block_addr - __end_block_addr);
@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+#![cfg(target_arch = "arm")]
+use defmt::*;
+use defmt_rtt as _;
+use panic_probe as _;
+
+// The firmware shared with the Pico, see raspberry-pi-pico/src/lib.rs, on the HAL and the pin map
+// its board-pico2 feature picks. main only takes the chip's peripherals, firmware.rs sets them up
+// and runs the rest.
+use raspberry_pi_pico::firmware::Firmware;
+use raspberry_pi_pico::hal::{self, pac};
+
+// Tells the boot ROM this is an Arm executable
+#[link_section = ".start_block"]
+#[used]
+pub static IMAGE_DEF: hal::block::ImageDef = hal::block::ImageDef::secure_exe();
+
+#[hal::entry]
+fn main() -> ! {
+    info!("Program start");
+    let pac = pac::Peripherals::take().unwrap();
+    let core = cortex_m::Peripherals::take().unwrap();
+    Firmware::set_up(pac, core).run()
+}
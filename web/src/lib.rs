@@ -0,0 +1,205 @@
+// Runs the game in a browser. The module only exports a handful of plain functions and imports
+// nothing, so index.html can drive it without any generated glue, and any wasm runtime can load it.
+use core::convert::Infallible;
+use embedded_graphics::geometry::{OriginDimensions, Size};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::DrawTarget;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+use program::abstract_device::{AbstractDevice, Inputs};
+use program::framebuffer::TooLarge;
+use program::game::Game;
+use std::cell::RefCell;
+
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 32;
+// RGBA, the blue OLED look of the desktop build
+const OFF: [u8; 4] = [0, 20, 40, 255];
+const ON: [u8; 4] = [0, 210, 255, 255];
+// After the tab was in the background, skip ahead instead of replaying every missed tick
+const MAX_CATCH_UP_MS: f64 = 250.0;
+
+#[derive(Debug)]
+pub enum Error {
+    Format,
+    // The canvas is larger than the game's frame
+    TooLarge,
+}
+
+impl From<Infallible> for Error {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+impl From<core::fmt::Error> for Error {
+    fn from(_value: core::fmt::Error) -> Self {
+        Self::Format
+    }
+}
+
+impl From<TooLarge> for Error {
+    fn from(_value: TooLarge) -> Self {
+        Self::TooLarge
+    }
+}
+
+// RGBA pixels in the layout of a canvas ImageData, so the page can copy them straight over
+pub struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Canvas {
+            pixels: OFF.repeat((WIDTH * HEIGHT) as usize),
+        }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 || point.x as u32 >= WIDTH || point.y as u32 >= HEIGHT {
+                continue;
+            }
+            let index = (point.y as u32 * WIDTH + point.x as u32) as usize * 4;
+            let rgba = if color.is_on() { ON } else { OFF };
+            self.pixels[index..index + 4].copy_from_slice(&rgba);
+        }
+        Ok(())
+    }
+}
+
+pub struct Device {
+    canvas: Canvas,
+    inputs: Inputs,
+    seed: u64,
+    flushed: bool,
+}
+
+impl AbstractDevice for Device {
+    type Display = Canvas;
+    type Error = Error;
+
+    fn get_inputs(&mut self) -> Result<Inputs, Error> {
+        Ok(self.inputs)
+    }
+
+    fn set_led(&mut self, _new_state: bool) {}
+
+    // The browser can't block, Runner waits between ticks instead
+    fn delay_ms(&mut self, _ms: u32) {}
+
+    fn get_rng_seed(&mut self) -> u64 {
+        self.seed
+    }
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.canvas
+    }
+
+    fn flush_display(&mut self, _area: Rectangle) -> Result<(), Error> {
+        self.flushed = true;
+        Ok(())
+    }
+}
+
+// Ticks the game from the page's animation frames. Ticks run on their own clock, several per frame
+// when needed, so the debouncer polls the buttons as often as on the hardware.
+pub struct Runner {
+    game: Game<'static, Device>,
+    next_tick_ms: Option<f64>,
+}
+
+impl Runner {
+    pub fn new(seed: u64) -> Result<Self, Error> {
+        let device = Device {
+            canvas: Canvas::new(),
+            inputs: Inputs::default(),
+            seed,
+            flushed: false,
+        };
+        Ok(Runner {
+            game: Game::new(device)?,
+            next_tick_ms: None,
+        })
+    }
+
+    // Returns whether the canvas changed
+    pub fn frame(&mut self, now_ms: f64, inputs: Inputs) -> Result<bool, Error> {
+        let mut next_tick_ms = match self.next_tick_ms {
+            Some(ms) if now_ms - ms <= MAX_CATCH_UP_MS => ms,
+            _ => now_ms,
+        };
+        self.game.device().inputs = inputs;
+        while next_tick_ms <= now_ms {
+            next_tick_ms += self.game.tick()? as f64;
+        }
+        self.next_tick_ms = Some(next_tick_ms);
+        let device = self.game.device();
+        Ok(core::mem::take(&mut device.flushed))
+    }
+
+    pub fn pixels(&mut self) -> &[u8] {
+        self.game.device().canvas.pixels()
+    }
+}
+
+thread_local! {
+    static RUNNER: RefCell<Option<Runner>> = const { RefCell::new(None) };
+}
+
+#[no_mangle]
+pub extern "C" fn start(seed: u32) {
+    let runner = Runner::new(seed as u64).expect("the game failed to start");
+    RUNNER.with_borrow_mut(|slot| *slot = Some(runner));
+}
+
+// Bit 0 is the first button, bit 1 the second. Returns 1 when the canvas needs to be redrawn.
+#[no_mangle]
+pub extern "C" fn frame(now_ms: f64, buttons: u32) -> u32 {
+    let inputs = Inputs {
+        button1_down: buttons & 1 != 0,
+        button2_down: buttons & 2 != 0,
+    };
+    RUNNER.with_borrow_mut(|slot| {
+        let runner = slot.as_mut().expect("start was not called");
+        runner.frame(now_ms, inputs).expect("the game failed") as u32
+    })
+}
+
+// Address of WIDTH * HEIGHT RGBA pixels in the module's memory
+#[no_mangle]
+pub extern "C" fn pixels() -> *const u8 {
+    RUNNER.with_borrow_mut(|slot| {
+        let runner = slot.as_mut().expect("start was not called");
+        runner.pixels().as_ptr()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn width() -> u32 {
+    WIDTH
+}
+
+#[no_mangle]
+pub extern "C" fn height() -> u32 {
+    HEIGHT
+}
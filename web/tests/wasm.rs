@@ -0,0 +1,83 @@
+// Builds the module for wasm32-unknown-unknown and runs it in wasmi, without a browser or any JS,
+// the same way index.html does: start, a few animation frames, then read the canvas pixels.
+
+use program::abstract_device::Inputs;
+use std::path::PathBuf;
+use std::process::Command;
+use wasmi::{Engine, Linker, Module, Store};
+use web::{Runner, HEIGHT, WIDTH};
+
+const SEED: u32 = 7;
+// Animation frame timestamps, a bit over a second at 60 Hz
+const FRAMES: std::ops::Range<u32> = 0..80;
+const FRAME_MS: f64 = 1000.0 / 60.0;
+
+fn build_module() -> Vec<u8> {
+    // A separate target directory, the one of this test run is locked while it builds
+    let target_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("wasm");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "-p", "web", "--target", "wasm32-unknown-unknown", "--release"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "building the wasm module failed");
+    std::fs::read(target_dir.join("wasm32-unknown-unknown/release/web.wasm")).unwrap()
+}
+
+fn run_module(wasm: &[u8]) -> Vec<u8> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm).unwrap();
+    let mut store = Store::new(&engine, ());
+    // Nothing to link: the page has to be able to load it without glue code
+    let linker = Linker::<()>::new(&engine);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+
+    let width = instance.get_typed_func::<(), u32>(&store, "width").unwrap();
+    let height = instance.get_typed_func::<(), u32>(&store, "height").unwrap();
+    assert_eq!(width.call(&mut store, ()).unwrap(), WIDTH);
+    assert_eq!(height.call(&mut store, ()).unwrap(), HEIGHT);
+
+    let start = instance.get_typed_func::<u32, ()>(&store, "start").unwrap();
+    let frame = instance.get_typed_func::<(f64, u32), u32>(&store, "frame").unwrap();
+    let pixels = instance.get_typed_func::<(), u32>(&store, "pixels").unwrap();
+    start.call(&mut store, SEED).unwrap();
+    let mut redraws = 0;
+    for i in FRAMES {
+        redraws += frame.call(&mut store, (i as f64 * FRAME_MS, 0)).unwrap();
+    }
+    assert!(redraws > 0);
+
+    let address = pixels.call(&mut store, ()).unwrap() as usize;
+    let memory = instance.get_memory(&store, "memory").unwrap();
+    memory.data(&store)[address..address + (WIDTH * HEIGHT * 4) as usize].to_vec()
+}
+
+#[test]
+fn test_boots_into_menu() {
+    let wasm_pixels = run_module(&build_module());
+
+    let mut runner = Runner::new(SEED as u64).unwrap();
+    for i in FRAMES {
+        runner.frame(i as f64 * FRAME_MS, Inputs::default()).unwrap();
+    }
+    assert!(wasm_pixels == runner.pixels(), "wasm and native builds drew different frames");
+
+    // Same picture as the first frame of the menu golden image
+    let golden = std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../program/tests/golden/menu.txt"),
+    )
+    .unwrap();
+    let menu: Vec<&str> = golden.lines().skip(1).take(HEIGHT as usize).collect();
+    for (y, row) in menu.iter().enumerate() {
+        for (x, pixel) in row.chars().enumerate() {
+            let index = (y * WIDTH as usize + x) * 4;
+            let on = wasm_pixels[index + 2] > 128;
+            assert_eq!(on, pixel == '#', "pixel {},{} differs from the menu", x, y);
+        }
+    }
+}